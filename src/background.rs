@@ -0,0 +1,77 @@
+use image::Rgba;
+use std::{convert::{From, TryFrom}, io, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// A solid background color.
+/// 
+/// Functions which take a background color, such as `encode::flatten` and
+/// `Image::blank`, accept either a `Background` or an `Rgba<u8>`.
+pub struct Background(pub Rgba<u8>);
+
+impl Background {
+    /// Opaque white.
+    pub const WHITE: Self = Background(Rgba([255, 255, 255, 255]));
+    /// Opaque black.
+    pub const BLACK: Self = Background(Rgba([0, 0, 0, 255]));
+    /// Fully transparent black.
+    pub const TRANSPARENT: Self = Background(Rgba([0, 0, 0, 0]));
+}
+
+impl Default for Background {
+    #[inline]
+    fn default() -> Self {
+        Self::TRANSPARENT
+    }
+}
+
+impl From<Rgba<u8>> for Background {
+    fn from(color: Rgba<u8>) -> Self {
+        Background(color)
+    }
+}
+
+impl From<Background> for Rgba<u8> {
+    fn from(bg: Background) -> Self {
+        bg.0
+    }
+}
+
+impl FromStr for Background {
+    type Err = io::Error;
+
+    /// Parses a color in the `#rrggbb` or `#rrggbbaa` hexadecimal notations.
+    /// 
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(bg)` if `s` is a valid hexadecimal color.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidInput))` otherwise.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid hexadecimal color: {:?}", s)
+        );
+
+        let hex = match s.len() {
+            7 | 9 if s.starts_with('#') => &s[1..],
+            _ => return Err(invalid())
+        };
+
+        let mut color = [255; 4];
+        for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+            *channel = hex.get(2 * i..2 * i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(invalid)?;
+        }
+
+        Ok(Background(Rgba(color)))
+    }
+}
+
+impl TryFrom<&str> for Background {
+    type Error = io::Error;
+
+    #[inline]
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
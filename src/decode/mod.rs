@@ -1,11 +1,14 @@
 //! Traits, types and functions to assist in decoding commonly used 
 //! _icon formats_.
 
-use crate::{load_raster, load_vector, Icon, Image};
+use crate::{load_raster, Icon, Image};
+#[cfg(feature = "svg")]
+use crate::{load_vector, SvgLimits};
 pub use error::DecodingError;
 use std::{io::{self, Read, Seek}};
 use image::{ImageFormat, DynamicImage};
-use resvg::usvg::Tree;
+#[cfg(feature = "svg")]
+use resvg::usvg::{self, Tree};
 
 mod error;
 
@@ -85,12 +88,41 @@ pub trait Decode<'a>: Sized {
     /// Parses and loads an icon family into memmory.
     fn read<R: Read + Seek>(r: R) -> Result<Self, DecodingError>;
 
+    /// Parses an icon family and returns the image associated with `icon`.
+    /// 
+    /// The default implementation loads the whole icon family into memmory.
+    /// Implementors of formats which store an index of their entries (such
+    /// as the directory of `.ico` files) should override it to seek to and
+    /// decode only the matching entry.
+    /// 
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(Some(image))` if the icon family contains `icon`.
+    /// * Returns `Ok(None)` if the icon family does not contain `icon`.
+    /// * Returns `Err(_)` if the icon family could not be parsed.
+    fn read_icon<R: Read + Seek>(
+        r: R,
+        icon: &Self::Icon
+    ) -> Result<Option<Image>, DecodingError> {
+        Ok(Self::read(r)?.get(icon).cloned())
+    }
+
     /// Returns the number of _icons_ contained in the icon family.
     fn len(&self) -> usize;
 
+    #[inline]
+    /// Returns `true` if the icon family contains no _icons_.
+    /// Otherwise returns `false`.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
     /// Returns `true` if the icon family contains `icon`.
     /// Otherwise returns `false`.
-    fn contains_icon(&self, icon: &Self::Icon) -> bool;
+    fn contains_icon(&self, icon: &Self::Icon) -> bool {
+        self.get(icon).is_some()
+    }
     
     /// Returns `Some(icon)` if the icon family contains `icon`.
     /// Otherwise returns `None`.
@@ -113,9 +145,64 @@ pub fn bmp<R: Read + Seek>(read: &mut R) -> io::Result<DynamicImage> {
     load_raster(read, ImageFormat::BMP)
 }
 
+#[cfg(feature = "svg")]
 #[inline]
 /// Converts _UTF8_-encoded _SVG_ strings to _vector graphics_.
 pub fn svg<R: Read + Seek>(read: &mut R) -> io::Result<Tree> {
-    load_vector(read)
+    load_vector(read, &usvg::Options::default(), SvgLimits::default())
+}
+
+
+/// Reads a series of named, encoded icons written by `encode::framed_write`
+/// until the end of the stream is reached.
+/// 
+/// # Return Value
+/// 
+/// * Returns `Ok(icons)` if the stream consists of well-formed frames.
+/// * Returns `Err(io::Error::from(io::ErrorKind::UnexpectedEof))` if the
+///   stream ends in the middle of a frame.
+/// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` if the name
+///   of an icon is not valid _UTF8_.
+pub fn framed_read<R: Read>(read: &mut R) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut icons = Vec::new();
+
+    while let Some(name) = read_frame(read, true)? {
+        let name = String::from_utf8(name)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let data = read_frame(read, false)?
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+        icons.push((name, data));
+    }
+
+    Ok(icons)
 }
 
+/// Reads a length-prefixed buffer from `read`, returning `Ok(None)` if
+/// `eof_ok` is set and the stream ends before the length prefix.
+fn read_frame<R: Read>(read: &mut R, eof_ok: bool) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+
+    // Check for the end of the stream before the first byte of the prefix
+    loop {
+        match read.read(&mut len[..1]) {
+            Ok(0) if eof_ok => return Ok(None),
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            Ok(_) => break,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err)
+        }
+    }
+
+    read.read_exact(&mut len[1..])?;
+    let len = u32::from_le_bytes(len);
+
+    let mut buf = Vec::new();
+    read.take(len as u64).read_to_end(&mut buf)?;
+
+    if buf.len() != len as usize {
+        Err(io::Error::from(io::ErrorKind::UnexpectedEof))
+    } else {
+        Ok(Some(buf))
+    }
+}
@@ -13,7 +13,9 @@ pub enum EncodingError<I: Icon + Send + Sync> {
     /// A resampling error.
     Resample(ResampleError),
     /// The icon family aready stores the maximum number of icons possible.
-    Full(u16)
+    Full(u16),
+    /// The dimensions of the source image exceed the maximum area allowed.
+    SourceTooLarge((u32, u32))
 }
 
 impl<I: Icon + Send + Sync> Display for EncodingError<I> {
@@ -22,11 +24,16 @@ impl<I: Icon + Send + Sync> Display for EncodingError<I> {
             Self::AlreadyIncluded(_) => write!(
                 f, "The icon family already contains this icon"
             ),
-            Self::Resample(err) => <ResampleError as Display>::fmt(&err, f),
+            Self::Resample(err) => <ResampleError as Display>::fmt(err, f),
             Self::Full(max_n) => write!(
                 f,
                 "The icon family has already reached it's maximum capacity ({} icons)",
                 max_n
+            ),
+            Self::SourceTooLarge((w, h)) => write!(
+                f,
+                "The source image is too large ({}x{} pixels)",
+                w, h
             )
         }
     }
@@ -41,7 +48,8 @@ impl<I: Icon + Send + Sync + Debug> Debug for EncodingError<I> {
                 e
             ),
             Self::Resample(err) => write!(f, "EncodingError::Resample({:?})", err),
-            Self::Full(n) => write!(f, "EncodingError::Full({})", n)
+            Self::Full(n) => write!(f, "EncodingError::Full({})", n),
+            Self::SourceTooLarge(size) => write!(f, "EncodingError::SourceTooLarge({:?})", size)
         }
     }
 }
@@ -70,10 +78,11 @@ impl<I: Icon + Send + Sync> From<io::Error> for EncodingError<I> {
 
 impl<I: Icon + Send + Sync> From<EncodingError<I>> for io::Error {
     fn from(err: EncodingError<I>) -> io::Error {
-        if let EncodingError::Resample(err) = err {
-            err.into()
-        } else {
-            io::Error::new(io::ErrorKind::InvalidInput, format!("{}", err))
+        match err {
+            EncodingError::Resample(err) => err.into(),
+            // A full icon family is not a problem with the input itself
+            EncodingError::Full(_) => io::Error::new(io::ErrorKind::Other, format!("{}", err)),
+            _ => io::Error::new(io::ErrorKind::InvalidInput, format!("{}", err))
         }
     }
 }
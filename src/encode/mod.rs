@@ -1,15 +1,26 @@
 //! Traits, types and functions to assist in encoding commonly used 
 //! _icon formats_.
 
-use crate::{Icon, Image};
-use image::{DynamicImage, ImageOutputFormat, ImageError};
-use std::{io::{self, BufWriter}, path::Path, fs::File};
-use resvg::usvg::{Tree, XmlIndent, XmlOptions};
+use crate::{Background, Icon, Image};
+use image::{DynamicImage, GenericImageView, ImageOutputFormat, ImageError, Rgb, RgbImage, Rgba};
+use png::Compression;
+use std::{
+    borrow::Cow,
+    collections::hash_map::{Entry, HashMap},
+    io::{self, BufWriter},
+    path::Path,
+    fs::File
+};
+#[cfg(feature = "svg")]
+use std::rc::Rc;
+#[cfg(feature = "svg")]
+use resvg::usvg::{Node, NodeKind, PathData, PathSegment, Tree, XmlIndent, XmlOptions};
 pub use error::EncodingError;
 
 mod error;
 
-const XML_OPTS: XmlOptions = XmlOptions {
+#[cfg(feature = "svg")]
+pub(crate) const XML_OPTS: XmlOptions = XmlOptions {
     indent: XmlIndent::None,
     attributes_indent: XmlIndent::None,
     use_single_quote: false,
@@ -45,7 +56,7 @@ const XML_OPTS: XmlOptions = XmlOptions {
 ///
 /// #[derive(Clone)]
 /// pub struct IconFamily<Icon: ikon::Icon + Send + Sync> {
-///     internal: HashMap<(u32, u32), DynamicImage>,
+///     internal: HashMap<(u32, u32), (Icon, DynamicImage)>,
 ///     phantom: marker::PhantomData<Icon>
 /// }
 /// 
@@ -57,6 +68,18 @@ const XML_OPTS: XmlOptions = XmlOptions {
 ///         self.internal.len()
 ///     }
 /// 
+///     fn contains_icon(&self, icon: &Self::Icon) -> bool {
+///         self.internal.contains_key(&icon.size())
+///     }
+/// 
+///     fn icons(&self) -> Vec<&Self::Icon> {
+///         self.internal.values().map(|(icon, _)| icon).collect()
+///     }
+/// 
+///     fn remove_icon(&mut self, icon: &Self::Icon) -> bool {
+///         self.internal.remove(&icon.size()).is_some()
+///     }
+/// 
 ///     fn add_icon<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
 ///         &mut self,
 ///         filter: F,
@@ -66,7 +89,7 @@ const XML_OPTS: XmlOptions = XmlOptions {
 ///         let size = icon.size();
 /// 
 ///         if let Entry::Vacant(entry) = self.internal.entry(size) {
-///             entry.insert(source.rasterize(filter, size)?);
+///             entry.insert((icon, source.rasterize(filter, size)?));
 ///             Ok(self)
 ///         } else {
 ///             Err(EncodingError::AlreadyIncluded(icon))
@@ -80,6 +103,41 @@ pub trait Encode: Sized {
     /// Returns the number of _icons_ contained in the icon.
     fn len(&self) -> usize;
 
+    #[inline]
+    /// Returns `true` if the icon family contains no _icons_.
+    /// Otherwise returns `false`.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the icon family contains `icon`.
+    /// Otherwise returns `false`.
+    /// 
+    /// The default implementation compares the size of `icon` to the sizes
+    /// of the _icons_ returned by `Encode::icons`. Implementors which can
+    /// look up their entries directly should override it.
+    fn contains_icon(&self, icon: &Self::Icon) -> bool {
+        let size = icon.size();
+        self.icons().iter().any(|other| other.size() == size)
+    }
+
+    /// Returns the _icons_ contained in the icon family.
+    fn icons(&self) -> Vec<&Self::Icon>;
+
+    /// Returns the sum of the areas of the _icons_ contained in the icon
+    /// family, in pixels.
+    /// 
+    /// Usefull for estimating the memory footprint of an icon family.
+    fn total_pixels(&self) -> u64 {
+        self.icons()
+            .iter()
+            .map(|icon| {
+                let (width, height) = icon.size();
+                width as u64 * height as u64
+            })
+            .sum()
+    }
+
     /// Adds an individual icon to the icon family.
     ///
     /// # Arguments
@@ -103,6 +161,14 @@ pub trait Encode: Sized {
         icon: Self::Icon,
     ) -> Result<&mut Self, EncodingError<Self::Icon>>;
 
+    /// Removes an individual icon from the icon family.
+    ///
+    /// # Return Value
+    ///
+    /// * Returns `true` if the icon family contained `icon`.
+    /// * Otherwise returns `false`.
+    fn remove_icon(&mut self, icon: &Self::Icon) -> bool;
+
     /// Adds a series of icons to the icon family.
     ///
     /// # Arguments
@@ -134,6 +200,74 @@ pub trait Encode: Sized {
 
         Ok(self)
     }
+
+    #[cfg(feature = "parallel")]
+    /// Adds a series of icons to the icon family, resampling `source` for
+    /// all of them concurrently.
+    /// 
+    /// The resampled icons are then added one by one with `Encode::add_icon`,
+    /// whose filter hands each of them over exactly once, along with an empty
+    /// placeholder source.
+    /// 
+    /// Only available with the `parallel` feature enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` The resampling filter that will be used to re-scale `source`.
+    /// * `source` A reference to the source image this icon will be based on.
+    /// * `icons` A container for the information on the target icons.
+    ///
+    /// # Return Value
+    ///
+    /// * Returns `Err(EncodingError::AlreadyIncluded(_))` if the icon family
+    ///   already contains any of the items of `icons`. This is checked with
+    ///   `Encode::contains_icon` before anything is resampled.
+    /// * Returns `Err(EncodingError::Resample(_))` if the resampling filter 
+    ///   provided in the `filter` argument fails or produces results of 
+    ///   dimensions other than the ones specified by the items of `icons`.
+    /// * Returns `Err(EncodingError::AlreadyIncluded(_))` if `icons` itself
+    ///   contains duplicates, once every icon has been resampled.
+    /// * Otherwise returns `Ok(())`.
+    fn add_icons_parallel<
+        F: Fn(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> + Sync,
+        I: IntoIterator<Item = Self::Icon>
+    >(
+        &mut self,
+        filter: F,
+        source: &Image,
+        icons: I,
+    ) -> Result<&mut Self, EncodingError<Self::Icon>> {
+        use rayon::prelude::*;
+
+        let mut icons: Vec<Self::Icon> = icons.into_iter().collect();
+        if let Some(i) = icons.iter().position(|icon| self.contains_icon(icon)) {
+            return Err(EncodingError::AlreadyIncluded(icons.swap_remove(i)));
+        }
+
+        // Parsed documents can't be shared between threads, so each thread
+        // gets a rasterizer of it's own
+        let rasterized = icons.par_iter()
+            .map_init(|| source.rasterizer(), |rasterizer, icon| match rasterizer {
+                Ok(rasterizer) => rasterizer.rasterize(&filter, icon.size()),
+                Err(err) => Err(io::Error::new(err.kind(), err.to_string()).into())
+            })
+            .collect::<Result<Vec<DynamicImage>, _>>()?;
+
+        // The icons are inserted sequentially to preserve the semantics
+        // of add_icon. The filter hands the resampled images over instead
+        // of copying them, so the source is only an empty placeholder
+        let placeholder = Image::transparent(0, 0);
+        for (icon, image) in icons.into_iter().zip(rasterized) {
+            let mut image = Some(image);
+            let hand_over = |_: &DynamicImage, _| {
+                image.take().ok_or_else(|| io::Error::from(io::ErrorKind::Other))
+            };
+
+            self.add_icon(hand_over, &placeholder, icon)?;
+        }
+
+        Ok(self)
+    }
 }
 
 /// The `Write` trait provides functionality for writing the
@@ -153,38 +287,444 @@ pub trait Write: Encode {
 pub trait Save: Encode {
     /// Writes the contents of the icon family to disk.
     fn save<P: AsRef<Path>>(&mut self, path: &P) -> io::Result<&mut Self>;
+
+    /// Writes the contents of the icon family to disk, calling `callback`
+    /// with the total number of bytes written so far as the write
+    /// progresses.
+    /// 
+    /// Usefull for displaying progress when saving large icon families.
+    /// Implementors of `Write` get progress reports for free, on every call
+    /// to `io::Write::write`.
+    /// 
+    /// The default implementation gives no progress at all: it simply calls
+    /// `Save::save` and never calls `callback`. Other implementors, such as
+    /// icon families saved as a directory of files, have to override this
+    /// method to report the bytes they write.
+    fn save_with_callback<P: AsRef<Path>, C: FnMut(usize)>(
+        &mut self,
+        path: &P,
+        _callback: C
+    ) -> io::Result<&mut Self> {
+        self.save(path)
+    }
 }
 
 impl<T: Write> Save for T {
     #[inline]
     fn save<P: AsRef<Path>>(&mut self, path: &P) -> io::Result<&mut Self> {
-        let mut file = BufWriter::new(File::create(path)?);
-        self.write(&mut file)
+        self.save_with_callback(path, |_| ())
+    }
+
+    fn save_with_callback<P: AsRef<Path>, C: FnMut(usize)>(
+        &mut self,
+        path: &P,
+        callback: C
+    ) -> io::Result<&mut Self> {
+        let path = path.as_ref();
+
+        // Include the offending path in the error message
+        let with_path = |err: io::Error| io::Error::new(
+            err.kind(),
+            format!("{}: {}", path.display(), err)
+        );
+
+        // Wrap the buffer rather than the file, so that the progress is
+        // reported on every write instead of whenever the buffer is flushed
+        let file = File::create(path).map_err(with_path)?;
+        let mut file = Progress { inner: BufWriter::new(file), written: 0, callback };
+        self.write(&mut file).map_err(with_path)?;
+        io::Write::flush(&mut file).map_err(with_path)?;
+
+        Ok(self)
     }
 }
 
+/// A writer which reports the number of bytes written through it.
+struct Progress<W: io::Write, C: FnMut(usize)> {
+    inner: W,
+    written: usize,
+    callback: C
+}
+
+impl<W: io::Write, C: FnMut(usize)> io::Write for Progress<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.written += len;
+        (self.callback)(self.written);
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The default maximum area of the source images accepted by
+/// `encode::check_source`, in pixels.
+pub const MAX_SOURCE_PIXELS: u64 = 8192 * 8192;
+
+/// Checks that the area of `source` does not exceed `max_pixels`.
+/// 
+/// Meant to be called by `Encode::add_icon` implementations before
+/// resampling, so that huge inputs (such as decompression bombs) are
+/// rejected before consuming large amounts of memory. Only _raster
+/// graphics_ are checked, since _vector graphics_ are rendered directly at
+/// the size of the target icon.
+/// 
+/// # Return Value
+/// 
+/// * Returns `Err(EncodingError::SourceTooLarge(_))` if the area of
+///   `source` exceeds `max_pixels`.
+/// * Otherwise returns `Ok(())`.
+pub fn check_source<I: Icon + Send + Sync>(
+    source: &Image,
+    max_pixels: u64
+) -> Result<(), EncodingError<I>> {
+    if let Some(ras) = source.as_raster() {
+        let (width, height) = ras.dimensions();
+
+        if width as u64 * height as u64 > max_pixels {
+            return Err(EncodingError::SourceTooLarge((width, height)));
+        }
+    }
+
+    Ok(())
+}
+
 #[inline]
 /// Converts _raster graphics_ to _PNG_-encoded buffers.
 pub fn png<W: io::Write>(image: &DynamicImage, w: &mut W) -> io::Result<()> {
-    image
-        .write_to(w, ImageOutputFormat::PNG)
-        .map_err(image_err_to_io)
+    png_with(image, w, Compression::Default, png::FilterType::Sub)
+}
+
+/// Converts _raster graphics_ to _PNG_-encoded buffers, using the specified
+/// compression level and scanline filter.
+/// 
+/// Higher compression levels trade encoding time for smaller buffers.
+pub fn png_with<W: io::Write>(
+    image: &DynamicImage,
+    w: &mut W,
+    compression: Compression,
+    filter: png::FilterType
+) -> io::Result<()> {
+    let (width, height) = image.dimensions();
+    let (data, color) = match image {
+        DynamicImage::ImageLuma8(img)  => (Cow::Borrowed(&**img), png::ColorType::Grayscale),
+        DynamicImage::ImageLumaA8(img) => (Cow::Borrowed(&**img), png::ColorType::GrayscaleAlpha),
+        DynamicImage::ImageRgb8(img)   => (Cow::Borrowed(&**img), png::ColorType::RGB),
+        DynamicImage::ImageRgba8(img)  => (Cow::Borrowed(&**img), png::ColorType::RGBA),
+        _ => (Cow::Owned(image.to_rgba().into_raw()), png::ColorType::RGBA)
+    };
+
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(compression);
+    encoder.set_filter(filter);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&data))
+        .map_err(png_err_to_io)
+}
+
+/// Converts _raster graphics_ to 8-bit indexed _PNG_-encoded buffers.
+/// 
+/// Small icons often use only a handful of colors, in which case an indexed
+/// _PNG_ can be a fraction of the size of it's _RGBA_ counterpart. Images
+/// with more than 256 distinct colors are encoded with `encode::png`
+/// instead, so no color information is ever lost.
+pub fn png_indexed<W: io::Write>(image: &DynamicImage, w: &mut W) -> io::Result<()> {
+    let rgba = image.to_rgba();
+    let mut palette: HashMap<[u8;4], u8> = HashMap::with_capacity(256);
+    let mut indexes = Vec::with_capacity(rgba.len() / 4);
+
+    for px in rgba.pixels() {
+        let next = palette.len();
+        let index = match palette.entry(px.0) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(_) if next == 256 => return png(image, w),
+            Entry::Vacant(entry) => *entry.insert(next as u8),
+        };
+
+        indexes.push(index);
+    }
+
+    let mut colors = vec![[0;4]; palette.len()];
+    for (color, index) in palette {
+        colors[index as usize] = color;
+    }
+
+    let plte: Vec<u8> = colors.iter().flat_map(|c| c[..3].to_vec()).collect();
+    let mut trns: Vec<u8> = colors.iter().map(|c| c[3]).collect();
+    while trns.last() == Some(&0xff) {
+        trns.pop();
+    }
+
+    let (width, height) = rgba.dimensions();
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_filter(png::FilterType::NoFilter);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| {
+            writer.write_chunk(*b"PLTE", &plte)?;
+            if !trns.is_empty() {
+                writer.write_chunk(*b"tRNS", &trns)?;
+            }
+
+            writer.write_image_data(&indexes)
+        })
+        .map_err(png_err_to_io)
 }
 
 #[inline]
 /// Converts _raster graphics_ to _BMP_-encoded buffers.
+/// 
+/// Since the encoded buffers have no alpha channel, `image` is flattened
+/// over an opaque white background first. Use `encode::bmp_with_background`
+/// to choose another background color.
 pub fn bmp<W: io::Write>(image: &DynamicImage, w: &mut W) -> io::Result<()> {
-    image
+    bmp_with_background(image, w, Background::WHITE)
+}
+
+/// Converts _raster graphics_ to _BMP_-encoded buffers, flattening `image`
+/// over a solid background of color `background`.
+/// 
+/// `background` may be an `Rgba<u8>` or a `Background`.
+pub fn bmp_with_background<W: io::Write>(
+    image: &DynamicImage,
+    w: &mut W,
+    background: impl Into<Rgba<u8>>
+) -> io::Result<()> {
+    flatten(image, background)
         .write_to(w, ImageOutputFormat::BMP)
         .map_err(image_err_to_io)
 }
 
+/// Converts _raster graphics_ to _JPEG_-encoded buffers with the specified
+/// `quality`, ranging from `1` to `100`.
+/// 
+/// Since the encoded buffers have no alpha channel, `image` is flattened
+/// over an opaque white background first.
+/// 
+/// # Return Value
+/// 
+/// * Returns `Err(io::Error::from(io::ErrorKind::InvalidInput))` if
+///   `quality` is out of range.
+/// * Returns `Err(_)` if writing to `w` fails.
+/// * Otherwise returns `Ok(())`.
+pub fn jpeg<W: io::Write>(image: &DynamicImage, w: &mut W, quality: u8) -> io::Result<()> {
+    if !(1..=100).contains(&quality) {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+
+    flatten(image, Background::WHITE)
+        .write_to(w, ImageOutputFormat::JPEG(quality))
+        .map_err(image_err_to_io)
+}
+
+/// Composites `image` over a solid background of color `background`,
+/// returning an image with no alpha channel.
+/// 
+/// Usefull for encoding to formats that can't represent transparency. The
+/// alpha channel of `background` is ignored.
+pub fn flatten(image: &DynamicImage, background: impl Into<Rgba<u8>>) -> DynamicImage {
+    let background = background.into();
+    let rgba = image.to_rgba();
+    let flat = RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let px = rgba.get_pixel(x, y);
+        let alpha = px[3] as u32;
+        let blend = |c: usize| {
+            ((px[c] as u32 * alpha + background[c] as u32 * (255 - alpha) + 127) / 255) as u8
+        };
+
+        Rgb([blend(0), blend(1), blend(2)])
+    });
+
+    DynamicImage::ImageRgb8(flat)
+}
+
+#[cfg(feature = "webp-encoder")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The compression scheme used by `encode::webp`.
+pub enum WebpCompression {
+    /// Lossless compression.
+    Lossless,
+    /// Lossy compression with the specified quality, ranging from `0.0` to
+    /// `100.0`.
+    Lossy(f32)
+}
+
+#[cfg(feature = "webp-encoder")]
+/// Converts _raster graphics_ to _WebP_-encoded buffers.
+/// 
+/// Requires the `webp-encoder` feature, which links to `libwebp`.
+/// 
+/// # Return Value
+/// 
+/// * Returns `Err(io::Error::from(io::ErrorKind::InvalidInput))` if the
+///   quality of `WebpCompression::Lossy` is out of range.
+/// * Returns `Err(_)` of kind `io::ErrorKind::InvalidInput` if _libwebp_
+///   can't encode `image`, such as when it's dimensions exceed `16383`.
+/// * Returns `Err(_)` if writing to `w` fails.
+/// * Otherwise returns `Ok(())`.
+pub fn webp<W: io::Write>(
+    image: &DynamicImage,
+    w: &mut W,
+    compression: WebpCompression
+) -> io::Result<()> {
+    let (lossless, quality) = match compression {
+        WebpCompression::Lossless => (true, 75.0),
+        WebpCompression::Lossy(quality) if (0.0..=100.0).contains(&quality) => (false, quality),
+        WebpCompression::Lossy(_) => return Err(io::Error::from(io::ErrorKind::InvalidInput))
+    };
+
+    let rgba = image.to_rgba();
+    let (width, height) = rgba.dimensions();
+
+    let buf = libwebp::Encoder::from_rgba(&rgba, width, height)
+        .encode_simple(lossless, quality)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}", err)))?;
+
+    w.write_all(&buf)
+}
+
+#[cfg(feature = "svg")]
 #[inline]
 /// Converts _vector graphics_ to _UTF8_-encoded _SVG_ strings.
 pub fn svg<W: io::Write>(image: &Tree, w: &mut W) -> io::Result<()> {
     w.write_all(image.to_string(XML_OPTS).as_ref())
 }
 
+#[cfg(feature = "svg")]
+/// Converts _vector graphics_ to minified _UTF8_-encoded _SVG_ strings.
+/// 
+/// In addition to the normalization performed by `usvg` (which already
+/// omits attributes set to their default values), empty groups are removed,
+/// path coordinates are rounded to `precision` decimal places and `usvg`'s
+/// metadata attributes are dropped. Use `encode::svg` for lossless output.
+pub fn svg_optimized<W: io::Write>(image: &Tree, w: &mut W, precision: u8) -> io::Result<()> {
+    let optimized = deep_copy(image);
+    let scale = 10f64.powi(precision as i32);
+    let round = |v: f64| (v * scale).round() / scale;
+
+    // Round the coordinates of all paths
+    for mut node in optimized.root().descendants() {
+        if let NodeKind::Path(ref mut path) = *node.borrow_mut() {
+            let segments = path.data.iter().map(|segment| match *segment {
+                PathSegment::MoveTo { x, y } => PathSegment::MoveTo { x: round(x), y: round(y) },
+                PathSegment::LineTo { x, y } => PathSegment::LineTo { x: round(x), y: round(y) },
+                PathSegment::CurveTo { x1, y1, x2, y2, x, y } => PathSegment::CurveTo {
+                    x1: round(x1), y1: round(y1),
+                    x2: round(x2), y2: round(y2),
+                    x: round(x), y: round(y)
+                },
+                PathSegment::ClosePath => PathSegment::ClosePath
+            });
+
+            path.data = Rc::new(PathData(segments.collect()));
+        }
+    }
+
+    // Remove empty groups, children first so that groups containing only
+    // empty groups get removed as well
+    let mut groups: Vec<Node> = optimized.root()
+        .descendants()
+        .filter(|node| if let NodeKind::Group(_) = *node.borrow() { true } else { false })
+        .collect();
+
+    while let Some(mut group) = groups.pop() {
+        if !group.has_children() {
+            group.detach();
+        }
+    }
+
+    let xml = optimized.to_string(XML_OPTS);
+    let xml = strip_attribute(&xml, "xmlns:usvg");
+    let xml = strip_attribute(&xml, "usvg:version");
+
+    w.write_all(xml.as_ref())
+}
+
+#[cfg(feature = "svg")]
+/// Creates a copy of `tree` which shares no nodes with it.
+fn deep_copy(tree: &Tree) -> Tree {
+    let copy = Tree::create(*tree.svg_node());
+
+    for child in tree.defs().children() {
+        copy.defs().append(child.clone().make_deep_copy());
+    }
+
+    for child in tree.root().children().skip(1) {
+        copy.root().append(child.clone().make_deep_copy());
+    }
+
+    copy
+}
+
+#[cfg(feature = "svg")]
+/// Removes the first occurence of the attribute `name` from a serialized
+/// _XML_ document.
+fn strip_attribute(xml: &str, name: &str) -> String {
+    let pattern = format!(" {}=\"", name);
+
+    match xml.find(&pattern) {
+        Some(start) => {
+            let value_start = start + pattern.len();
+            let end = xml[value_start..].find('"').map_or(xml.len(), |i| value_start + i + 1);
+
+            format!("{}{}", &xml[..start], &xml[end..])
+        },
+        None => String::from(xml)
+    }
+}
+
+/// Writes a series of named, encoded icons to `w` as length-prefixed 
+/// frames, allowing multiple icons to be piped through a single stream.
+/// 
+/// Each frame is written as `[name_len][name][data_len][data]`, where
+/// `name_len` and `data_len` are little-endian `u32`s. Frames can be read
+/// back with `decode::framed_read`.
+/// 
+/// # Return Value
+/// 
+/// * Returns `Err(io::Error::from(io::ErrorKind::InvalidInput))` if the
+///   length of a name or buffer does not fit in a `u32`.
+/// * Returns `Err(_)` if writing to `w` fails.
+/// * Otherwise returns `Ok(())`.
+pub fn framed_write<W: io::Write>(icons: &[(&str, &[u8])], w: &mut W) -> io::Result<()> {
+    for (name, data) in icons {
+        write_frame(name.as_bytes(), w)?;
+        write_frame(data, w)?;
+    }
+
+    Ok(())
+}
+
+#[inline]
+/// Writes `buf` to `w` prefixed by it's length.
+fn write_frame<W: io::Write>(buf: &[u8], w: &mut W) -> io::Result<()> {
+    if buf.len() > std::u32::MAX as usize {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+
+    w.write_all(&(buf.len() as u32).to_le_bytes())?;
+    w.write_all(buf)
+}
+
+#[inline]
+/// Convert a `png::EncodingError` to an `io::Error`
+fn png_err_to_io(err: png::EncodingError) -> io::Error {
+    match err {
+        png::EncodingError::IoError(err) => err,
+        png::EncodingError::Format(msg) => io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+}
+
 #[inline]
 /// Convert an `ImageError` to an `io::Error`
 fn image_err_to_io(err: ImageError) -> io::Error {
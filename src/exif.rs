@@ -0,0 +1,83 @@
+//! Extraction of the _EXIF_ orientation tag from _JPEG_ files.
+
+use image::DynamicImage;
+use std::convert::TryInto;
+
+/// The marker of the `APP1` segment, which stores _EXIF_ metadata.
+const APP1: u8 = 0xe1;
+/// The marker of the start of the compressed image data.
+const SOS: u8 = 0xda;
+/// The _EXIF_ tag of the orientation of the image.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// Returns the value of the _EXIF_ orientation tag of a _JPEG_ file, if
+/// present.
+pub(crate) fn orientation(jpeg: &[u8]) -> Option<u16> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+
+    let mut data = &jpeg[2..];
+    while data.len() >= 4 && data[0] == 0xff {
+        let marker = data[1];
+        if marker == SOS {
+            return None;
+        }
+
+        let len = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let segment = data.get(4..2 + len)?;
+
+        if marker == APP1 && segment.starts_with(b"Exif\0\0") {
+            return tiff_orientation(&segment[6..]);
+        }
+
+        data = &data[(2 + len).min(data.len())..];
+    }
+
+    None
+}
+
+/// Applies an _EXIF_ orientation to `image`, so that it is displayed upright.
+pub(crate) fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image
+    }
+}
+
+/// Searches the first _IFD_ of a _TIFF_ structure for the orientation tag.
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(..2)? {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None
+    };
+
+    let u16_at = |offset: usize| -> Option<u16> {
+        let bytes = tiff.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    };
+
+    let u32_at = |offset: usize| -> Option<u32> {
+        let bytes = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+
+    if u16_at(2)? != 42 {
+        return None;
+    }
+
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+
+    (0..count)
+        .map(|i| ifd + 2 + 12 * i)
+        .find(|&entry| u16_at(entry) == Some(ORIENTATION_TAG))
+        .and_then(|entry| u16_at(entry + 8))
+}
@@ -38,23 +38,50 @@
 //! module. The `resample` module also exposes the `resample::apply` function, 
 //! which applies a resampling filter to an _image_ and checks if the outputted 
 //! result matches the dimensions specified by the filter's arguments.
+//! 
+//! ## Features
+//! 
+//! Support for _vector graphics_ is provided by the `svg` feature, which is
+//! enabled by default. Raster-only users can disable it to avoid depending
+//! on [`resvg`](https://crates.io/crates/resvg), in which case
+//! `Image::load` rejects any stream that isn't _raster graphics_.
 
 pub extern crate image;
+#[cfg(feature = "svg")]
 pub extern crate resvg;
+pub extern crate png;
 
-use crate::{usvg::Tree, resample::ResampleError};
-use image::{DynamicImage, GenericImageView, ImageError, ImageFormat};
+use crate::resample::ResampleError;
+#[cfg(feature = "svg")]
+use crate::usvg::Tree;
+pub use background::Background;
+pub use rasterizer::Rasterizer;
+#[cfg(feature = "svg")]
+pub use svg::Svg;
+use image::{
+    gif, AnimationDecoder, DynamicImage, FilterType, GenericImageView, ImageError, ImageFormat,
+    Rgba, RgbaImage
+};
+#[cfg(feature = "svg")]
 pub use resvg::{raqote, usvg};
 use std::{
+    collections::hash_map::{Entry, HashMap},
     convert::From,
     fs::File,
-    io::{self, Read, Seek, BufReader, SeekFrom},
+    io::{self, Read, Seek, BufReader, Cursor, SeekFrom},
     path::Path,
+    sync::Arc,
 };
 
 pub mod resample;
 pub mod encode;
 pub mod decode;
+mod background;
+mod exif;
+mod rasterizer;
+#[cfg(feature = "svg")]
+mod svg;
+mod webp;
 #[cfg(test)]
 mod test;
 
@@ -64,13 +91,45 @@ pub trait Icon {
     fn size(&self) -> (u32, u32);
 }
 
+/// A function that decodes a byte stream of a custom format into an `Image`.
+/// 
+/// See `Image::load_with_detector`.
+pub type FormatHandler = fn(&mut dyn Read) -> io::Result<Image>;
+
+#[cfg(feature = "svg")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Limits on the size of _SVG_ documents accepted by 
+/// `Image::load_vector_limited`.
+pub struct SvgLimits {
+    /// The maximum length of the document in bytes.
+    pub max_bytes: u64,
+    /// The maximum number of elements in the document.
+    pub max_nodes: usize,
+}
+
+#[cfg(feature = "svg")]
+impl Default for SvgLimits {
+    /// No limits at all.
+    fn default() -> Self {
+        SvgLimits { max_bytes: std::u64::MAX, max_nodes: std::usize::MAX }
+    }
+}
+
 #[derive(Clone)]
+#[non_exhaustive]
 /// A uniun type for raster and vector graphics.
+/// 
+/// Cloning an `Image` is cheap: the pixel buffer of _raster graphics_ and
+/// the document of _vector graphics_ are reference-counted.
+/// 
+/// The `Image::Svg` variant only exists when the `svg` feature is enabled,
+/// so matches on an `Image` outside of this crate need a wildcard arm.
 pub enum Image {
     /// A generic raster image.
-    Raster(DynamicImage),
+    Raster(Arc<DynamicImage>),
     /// A svg-encoded vector image.
-    Svg(Tree),
+    #[cfg(feature = "svg")]
+    Svg(Svg),
 }
 
 impl Image {
@@ -91,6 +150,13 @@ impl Image {
     }
 
     /// Attempts to create a `Image` from a byte stream.
+    /// 
+    /// This is the default way of loading images: the format of the stream
+    /// is detected from it's signature. Use `Image::load_with_format` to
+    /// skip detection when the format is already known.
+    /// 
+    /// Only the first frame of animated _WebP_ streams is decoded, drawn onto
+    /// the canvas of the animation.
     ///
     /// # Return Value
     /// 
@@ -98,14 +164,43 @@ impl Image {
     ///   successfully parsed into an image.
     /// * Returns `Err(io::Error::from(io::ErrorKind::Other))` if the image allocation failed.
     /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidInput))` if the image format is not
-    ///   supported by `ikon`.
+    ///   supported by `ikon`, which includes any _vector graphics_ when the `svg` feature
+    ///   is disabled.
     /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` otherwise.
-    pub fn load<R: Read + Seek>(mut read: R) -> Result<Self, io::Error> {
+    pub fn load<R: Read + Seek>(read: R) -> Result<Self, io::Error> {
+        Self::load_with_detector(read, |_| None)
+    }
+
+    /// Attempts to create a `Image` from a byte stream, consulting `detector`
+    /// before the built-in signature table of `Image::load`.
+    /// 
+    /// `detector` is called with the first 8 bytes of the stream. If it
+    /// returns `Some(handler)`, `handler` is called with the stream rewinded
+    /// to it's start and it's output is returned. Otherwise the format is
+    /// detected as in `Image::load`.
+    /// 
+    /// There is no global registry of formats: a detector only affects the
+    /// call it is passed to, so this is safe to use from multiple threads
+    /// with different detectors.
+    ///
+    /// # Return Value
+    /// 
+    /// * Returns the output of the handler if `detector` returns one.
+    /// * Otherwise returns the same as `Image::load`.
+    pub fn load_with_detector<R, D>(mut read: R, detector: D) -> Result<Self, io::Error>
+    where
+        R: Read + Seek,
+        D: Fn(&[u8;8]) -> Option<FormatHandler>
+    {
         // Read the file's signature
         let mut signature: [u8;8] = [0;8];
         read.read_exact(&mut signature)?;
         read.seek(SeekFrom::Start(0))?;
 
+        if let Some(handler) = detector(&signature) {
+            return handler(&mut read);
+        }
+
         match signature {
             [0x89, b'P', b'N', b'G', 0xd, 0xa, 0x1a, 0xa] => {
                 load_raster(read, ImageFormat::PNG).map(Image::from)
@@ -121,12 +216,197 @@ impl Image {
                 load_raster(read, ImageFormat::BMP).map(Image::from)
             },
             [b'R', b'I', b'F', b'F', ..] => {
-                load_raster(read, ImageFormat::WEBP).map(Image::from)
+                load_webp(read).map(Image::from)
+            },
+            #[cfg(feature = "svg")]
+            _ => {
+                load_vector(read, &usvg::Options::default(), SvgLimits::default())
+                    .map(Image::from)
+            },
+            #[cfg(not(feature = "svg"))]
+            _ => Err(io::Error::from(io::ErrorKind::InvalidInput))
+        }
+    }
+
+    /// Returns the number of frames of the image encoded in a byte stream.
+    /// 
+    /// For animated _GIF_ and _WebP_ streams this is the number of frames of
+    /// the animation, in which case `Image::load` only decodes the first frame.
+    /// Any other stream is considered to have a single frame.
+    pub fn frame_count<R: Read + Seek>(mut read: R) -> Result<usize, io::Error> {
+        let mut signature: [u8;4] = [0;4];
+        read.read_exact(&mut signature)?;
+        read.seek(SeekFrom::Start(0))?;
+
+        match &signature {
+            b"RIFF" => {
+                let frames = webp::frames(&read_to_vec(read)?)?.len();
+                Ok(frames.max(1))
             },
-            _ => load_vector(read).map(Image::from)
+            b"GIF8" => load_gif_frames(read).map(|frames| frames.len()),
+            _ => Ok(1)
         }
     }
 
+    /// Attempts to decode every frame of an image encoded in a byte stream.
+    /// 
+    /// Frames of animated _GIF_ and _WebP_ streams are composited over the
+    /// previous ones, so that each of them is a complete image of the size of
+    /// the canvas of the animation. Any other _raster graphics_ are returned
+    /// as a single frame.
+    ///
+    /// # Return Value
+    /// 
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidInput))` if the
+    ///   stream is _vector graphics_.
+    /// * Otherwise returns the same errors as `Image::load`.
+    pub fn load_frames<R: Read + Seek>(mut read: R) -> Result<Vec<DynamicImage>, io::Error> {
+        let mut signature: [u8;4] = [0;4];
+        read.read_exact(&mut signature)?;
+        read.seek(SeekFrom::Start(0))?;
+
+        match &signature {
+            b"GIF8" => load_gif_frames(read),
+            b"RIFF" => {
+                let contents = read_to_vec(read)?;
+
+                match webp::Animation::new(&contents)? {
+                    Some(frames) => frames.map(|frame| frame.map(DynamicImage::ImageRgba8)).collect(),
+                    None => load_webp(Cursor::new(contents)).map(|frame| vec![frame])
+                }
+            },
+            _ => match Self::load(read)? {
+                Image::Raster(ras) => {
+                    Ok(vec![Arc::try_unwrap(ras).unwrap_or_else(|ras| (*ras).clone())])
+                },
+                #[cfg(feature = "svg")]
+                Image::Svg(_) => Err(io::Error::from(io::ErrorKind::InvalidInput))
+            }
+        }
+    }
+
+    #[inline]
+    /// Attempts to create a `Image` from a byte stream of _raster graphics_
+    /// encoded in `format`, skipping the signature-based format detection
+    /// performed by `Image::load`.
+    /// 
+    /// Usefull when the format is already known, such as from the MIME type
+    /// of an HTTP response.
+    ///
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(src)` if the stram indicated by the `read` argument could be
+    ///   successfully parsed into an image.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::Other))` if the image allocation failed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` otherwise.
+    pub fn load_with_format<R: Read + Seek>(
+        read: R,
+        format: ImageFormat
+    ) -> Result<Self, io::Error> {
+        load_raster(read, format).map(Image::from)
+    }
+
+    #[cfg(feature = "svg")]
+    #[inline]
+    /// Attempts to create a `Image` from an _SVG_ file, parsing it with the
+    /// options specified by `opts`.
+    /// 
+    /// See `Image::load_vector_with` for more information.
+    pub fn open_vector_with<P: AsRef<Path>>(
+        path: P,
+        opts: &usvg::Options
+    ) -> Result<Self, io::Error> {
+        Self::load_vector_with(File::open(path)?, opts)
+    }
+
+    #[cfg(feature = "svg")]
+    #[inline]
+    /// Attempts to create a `Image` from a byte stream of _UTF8_-encoded
+    /// _SVG_, parsing it with the options specified by `opts`.
+    /// 
+    /// `Image::load` always uses `usvg::Options::default()`. Supplying custom
+    /// options is how the default font (`opts.font_family` and 
+    /// `opts.font_size`) used to resolve text, the target DPI (`opts.dpi`)
+    /// used to convert physical units or the path used to resolve relative
+    /// image references (`opts.path`) are configured. Use 
+    /// `resample::svg_with_options` to render the image with the same
+    /// options.
+    ///
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(src)` if the stram indicated by the `read` argument could be
+    ///   successfully parsed into an image.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::Other))` if the image allocation failed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` otherwise.
+    pub fn load_vector_with<R: Read + Seek>(
+        read: R,
+        opts: &usvg::Options
+    ) -> Result<Self, io::Error> {
+        load_vector(read, opts, SvgLimits::default())
+            .map(|tree| Image::Svg(Svg::with_options(&tree, opts)))
+    }
+
+    #[cfg(feature = "svg")]
+    #[inline]
+    /// Attempts to create a `Image` from a byte stream of _UTF8_-encoded
+    /// _SVG_, rejecting documents that exceed `limits` before they are 
+    /// parsed.
+    /// 
+    /// Usefull for services that accept user-uploaded _SVGs_, since
+    /// pathologically large or deep documents can take a very long time
+    /// to parse.
+    ///
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(src)` if the stram indicated by the `read` argument could be
+    ///   successfully parsed into an image.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidInput))` if the
+    ///   document exceeds `limits`.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::Other))` if the image allocation failed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` otherwise.
+    pub fn load_vector_limited<R: Read + Seek>(
+        read: R,
+        opts: &usvg::Options,
+        limits: SvgLimits
+    ) -> Result<Self, io::Error> {
+        load_vector(read, opts, limits)
+            .map(|tree| Image::Svg(Svg::with_options(&tree, opts)))
+    }
+
+    #[cfg(feature = "svg")]
+    #[inline]
+    /// Attempts to create a `Image` from an _SVG_ document.
+    ///
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(src)` if the `svg` argument could be successfully parsed
+    ///   into an image.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::Other))` if the image allocation failed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` otherwise.
+    pub fn from_svg_str(svg: &str) -> Result<Self, io::Error> {
+        Tree::from_str(svg, &usvg::Options::default())
+            .map(Image::from)
+            .map_err(usvg_err_to_io)
+    }
+
+    #[inline]
+    /// Creates a `width`x`height` _raster graphics_ image filled with
+    /// `color`.
+    /// 
+    /// Usefull for placeholders, backgrounds and tests.
+    pub fn blank(width: u32, height: u32, color: impl Into<Rgba<u8>>) -> Self {
+        Image::from(DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, color.into())))
+    }
+
+    #[inline]
+    /// Creates a fully transparent `width`x`height` _raster graphics_
+    /// image.
+    /// 
+    /// Equivalent to `Image::blank(width, height, Rgba([0, 0, 0, 0]))`.
+    pub fn transparent(width: u32, height: u32) -> Self {
+        Self::blank(width, height, Background::TRANSPARENT)
+    }
+
     /// Rasterizes the `Image` to a `DynamicImage`.
     /// 
     /// For _raster graphics_ the moethod simply applies the resampling filter
@@ -139,25 +419,199 @@ impl Image {
         filter: F,
         size: (u32, u32),
     ) -> Result<DynamicImage, ResampleError> {
+        self.rasterizer()?.rasterize(filter, size)
+    }
+
+    /// Rasterizes the `Image` to a `DynamicImage` of exactly the dimensions
+    /// specified by `size`, stretching it if needed.
+    /// 
+    /// Unlike the built-in filters used with `Image::rasterize`, which
+    /// preserve the aspect ratio of the image and pad or crop it to fit
+    /// `size`, the image is resampled to the literal rectangle. This is
+    /// usefull for non-square targets such as banners. For _vector
+    /// graphics_, the image is rendered at a scale which covers `size` and
+    /// then resampled with `filter`.
+    pub fn rasterize_exact(
+        &self,
+        filter: FilterType,
+        size: (u32, u32),
+    ) -> Result<DynamicImage, ResampleError> {
+        self.rasterizer()?.rasterize_exact(filter, size)
+    }
+
+    #[inline]
+    /// Prepares the `Image` for repeated rasterization.
+    /// 
+    /// The document of _vector graphics_ is parsed once, when the
+    /// `Rasterizer` is created, rather than on every call to
+    /// `Image::rasterize`.
+    /// 
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(rasterizer)` if the image is _raster graphics_ or it's
+    ///   document could be parsed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::Other))` if the tree allocation failed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` otherwise.
+    pub fn rasterizer(&self) -> io::Result<Rasterizer<'_>> {
+        Rasterizer::new(self)
+    }
+
+    #[inline]
+    /// Rasterizes the `Image` to a series of `DynamicImage`s, one for each
+    /// item of `sizes`.
+    /// 
+    /// Equivalent to calling `Image::rasterize` for each item of `sizes`,
+    /// except that the document of _vector graphics_ is only parsed once,
+    /// and repeated sizes are only resampled (or rendered) once.
+    pub fn rasterize_many<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+        &self,
+        mut filter: F,
+        sizes: &[(u32, u32)],
+    ) -> Result<Vec<DynamicImage>, ResampleError> {
+        let rasterizer = self.rasterizer()?;
+        let mut cache: HashMap<(u32, u32), DynamicImage> = HashMap::with_capacity(sizes.len());
+
+        for &size in sizes {
+            if let Entry::Vacant(entry) = cache.entry(size) {
+                entry.insert(rasterizer.rasterize(|src, size| filter(src, size), size)?);
+            }
+        }
+
+        Ok(sizes.iter().map(|size| cache[size].clone()).collect())
+    }
+
+    /// Rasterizes the `Image` to a `DynamicImage`, compositing it over a
+    /// solid background.
+    /// 
+    /// Works as `Image::rasterize`, except that the output, including the
+    /// borders added to fit `size`, is composited over `background`. This is
+    /// usefull for targets that need an opaque fill. `background` may be an
+    /// `Rgba<u8>` or a `Background`.
+    pub fn rasterize_with_background<
+        F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>
+    >(
+        &self,
+        filter: F,
+        size: (u32, u32),
+        background: impl Into<Rgba<u8>>
+    ) -> Result<DynamicImage, ResampleError> {
+        let rasterized = self.rasterize(filter, size)?;
+        Ok(resample::overfit_with(&rasterized, size, background)?)
+    }
+
+    #[inline]
+    /// Returns `Some(ras)` if the image is _raster graphics_.
+    /// Otherwise returns `None`.
+    ///
+    /// _Vector graphics_ always return `None`.
+    pub fn as_raster(&self) -> Option<&DynamicImage> {
         match self {
-            Self::Raster(ras) => resample::apply(filter, ras, size),
-            Self::Svg(svg) => resample::svg(svg, size),
+            Image::Raster(ras) => Some(ras),
+            #[cfg(feature = "svg")]
+            Image::Svg(_) => None,
+        }
+    }
+
+    #[cfg(feature = "svg")]
+    #[inline]
+    /// Returns `Some(svg)` if the image is _vector graphics_.
+    /// Otherwise returns `None`.
+    ///
+    /// _Raster graphics_ always return `None`.
+    pub fn as_svg(&self) -> Option<&Svg> {
+        match self {
+            Image::Raster(_) => None,
+            Image::Svg(svg) => Some(svg),
+        }
+    }
+
+    #[inline]
+    /// Returns `true` if the image is _raster graphics_.
+    /// Otherwise returns `false`.
+    pub fn is_raster(&self) -> bool {
+        self.as_raster().is_some()
+    }
+
+    #[inline]
+    /// Returns `true` if the image is _vector graphics_.
+    /// Otherwise returns `false`.
+    pub fn is_svg(&self) -> bool {
+        !self.is_raster()
+    }
+
+    /// Converts _raster graphics_ to the `DynamicImage::ImageRgba8` color
+    /// type in place, so that subsequent conversions to _RGBA_ are free.
+    /// 
+    /// _Vector graphics_ are left unchanged.
+    pub fn ensure_rgba(&mut self) {
+        match self {
+            Image::Raster(ras) if ras.as_rgba8().is_none() => {
+                *ras = Arc::new(DynamicImage::ImageRgba8(ras.to_rgba()));
+            },
+            _ => {}
+        }
+    }
+
+    /// Computes a 64-bit perceptual hash of the image.
+    /// 
+    /// This is a [_difference hash_](http://www.hackerfactor.com/blog/index.php?/archives/529-Kind-of-Like-That.html)
+    /// computed from a downsampled grayscale version of the image: unlike
+    /// exact byte hashes, visually similar images (such as re-compressed
+    /// copies) produce hashes with a small 
+    /// [Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance), 
+    /// which can be computed as `(a ^ b).count_ones()`.
+    /// 
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(hash)` if the image is _raster graphics_ or it's
+    ///   document could be parsed and rendered.
+    /// * Returns `Err(_)` otherwise.
+    pub fn phash(&self) -> io::Result<u64> {
+        let gray = match self {
+            Image::Raster(ras) => ras.resize_exact(9, 8, FilterType::Triangle).to_luma(),
+            #[cfg(feature = "svg")]
+            Image::Svg(svg) => {
+                let ras = resample::svg_with_options(&svg.tree()?, (64, 64), svg.options())?;
+                ras.resize_exact(9, 8, FilterType::Triangle).to_luma()
+            }
+        };
+
+        let mut hash = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                hash <<= 1;
+                if gray.get_pixel(x, y)[0] < gray.get_pixel(x + 1, y)[0] {
+                    hash |= 1;
+                }
+            }
         }
+
+        Ok(hash)
     }
 
     /// Returns the width of the image in pixel units.
+    /// 
+    /// For _vector graphics_ this is the width of the view box, which is
+    /// resolved from the `width` attribute when the `viewBox` attribute is
+    /// absent.
     pub fn width(&self) -> f64 {
         match self {
             Image::Raster(ras) => ras.width() as f64,
-            Image::Svg(svg) => svg.svg_node().view_box.rect.width(),
+            #[cfg(feature = "svg")]
+            Image::Svg(svg) => svg.width(),
         }
     }
 
     /// Returns the height of the image in pixel units.
+    /// 
+    /// For _vector graphics_ this is the height of the view box, which is
+    /// resolved from the `height` attribute when the `viewBox` attribute is
+    /// absent.
     pub fn height(&self) -> f64 {
         match self {
             Image::Raster(ras) => ras.height() as f64,
-            Image::Svg(svg) => svg.svg_node().view_box.rect.height(),
+            #[cfg(feature = "svg")]
+            Image::Svg(svg) => svg.height(),
         }
     }
 
@@ -165,22 +619,69 @@ impl Image {
     pub fn dimensions(&self) -> (f64, f64) {
         (self.width(), self.height())
     }
+
+    /// Computes the bounding box of the visible content of the image as
+    /// `(x, y, width, height)`, in pixel units.
+    /// 
+    /// For _raster graphics_ this is the bounding box of the pixels whose
+    /// alpha is above `alpha_threshold`, which can be used to trim
+    /// transparent borders (see `resample::trimmed_with_threshold`). For
+    /// _vector graphics_ this is the view box, rounded up to whole pixels
+    /// and translated to the origin.
+    /// 
+    /// Returns `None` if the image is fully transparent.
+    pub fn content_bounds(&self, alpha_threshold: u8) -> Option<(u32, u32, u32, u32)> {
+        match self {
+            Image::Raster(ras) => resample::content_bounds(ras, alpha_threshold),
+            #[cfg(feature = "svg")]
+            Image::Svg(svg) => Some((0, 0, svg.width().ceil() as u32, svg.height().ceil() as u32)),
+        }
+    }
 }
 
+#[cfg(feature = "svg")]
 impl From<Tree> for Image {
     fn from(svg: Tree) -> Self {
-        Image::Svg(svg)
+        Image::Svg(Svg::from(svg))
     }
 }
 
 impl From<DynamicImage> for Image {
     fn from(bit: DynamicImage) -> Self {
-        Image::Raster(bit)
+        Image::Raster(Arc::new(bit))
     }
 }
 
-unsafe impl Send for Image {}
-unsafe impl Sync for Image {}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Semantic icon sizes, following the conventions of the _Windows_ shell.
+/// 
+/// | Variant  | Size      |
+/// |----------|-----------|
+/// | `Small`  | `16x16`   |
+/// | `Medium` | `32x32`   |
+/// | `Large`  | `48x48`   |
+/// | `XLarge` | `256x256` |
+pub enum StdSize {
+    /// A `16x16` icon.
+    Small,
+    /// A `32x32` icon.
+    Medium,
+    /// A `48x48` icon.
+    Large,
+    /// A `256x256` icon.
+    XLarge,
+}
+
+impl Icon for StdSize {
+    fn size(&self) -> (u32, u32) {
+        match self {
+            StdSize::Small  => (16, 16),
+            StdSize::Medium => (32, 32),
+            StdSize::Large  => (48, 48),
+            StdSize::XLarge => (256, 256),
+        }
+    }
+}
 
 impl Icon for (u32, u32) {
     fn size(&self) -> (u32, u32) {
@@ -201,22 +702,107 @@ impl Icon for (u8, u8) {
 }
 
 /// Loads raster graphics to an `Image`.
+/// 
+/// _JPEG_ files are rotated according to their _EXIF_ orientation tag, so
+/// that photos are loaded upright.
 fn load_raster<R: Read + Seek>(
     read: R, 
     format: ImageFormat
 ) -> io::Result<DynamicImage> {
-    match image::load(BufReader::new(read), format) {
-        Ok(img) => Ok(img),
-        Err(ImageError::InsufficientMemory) => {
-            Err(io::Error::from(io::ErrorKind::Other))
-        },
-        Err(ImageError::IoError(err)) => Err(err),
-        _ => Err(io::Error::from(io::ErrorKind::InvalidData))
+    if format == ImageFormat::JPEG {
+        let contents = read_to_vec(read)?;
+        let orientation = exif::orientation(&contents);
+
+        return decode_raster(Cursor::new(&contents), format).map(|img| match orientation {
+            Some(orientation) => exif::apply_orientation(img, orientation),
+            None => img
+        });
+    }
+
+    decode_raster(read, format)
+}
+
+/// Decodes every frame of _GIF_-encoded raster graphics.
+fn load_gif_frames<R: Read + Seek>(read: R) -> io::Result<Vec<DynamicImage>> {
+    gif::Decoder::new(BufReader::new(read))
+        .and_then(|decoder| decoder.into_frames().collect_frames())
+        .map(|frames| {
+            frames.into_iter()
+                .map(|frame| DynamicImage::ImageRgba8(frame.into_buffer()))
+                .collect()
+        })
+        .map_err(image_err_to_io)
+}
+
+/// Decodes raster graphics without any further processing.
+fn decode_raster<R: Read + Seek>(
+    read: R, 
+    format: ImageFormat
+) -> io::Result<DynamicImage> {
+    image::load(BufReader::new(read), format).map_err(image_err_to_io)
+}
+
+/// Converts an `ImageError` to an `io::Error`.
+fn image_err_to_io(err: ImageError) -> io::Error {
+    match err {
+        ImageError::InsufficientMemory => io::Error::from(io::ErrorKind::Other),
+        ImageError::IoError(err) => err,
+        _ => io::Error::from(io::ErrorKind::InvalidData)
     }
 }
 
+/// Loads _WebP_-encoded raster graphics to an `Image`, decoding only the
+/// first frame of animated streams.
+fn load_webp<R: Read + Seek>(read: R) -> io::Result<DynamicImage> {
+    let contents = read_to_vec(read)?;
+
+    if let Some(mut frames) = webp::Animation::new(&contents)? {
+        return frames.next()
+            .unwrap_or_else(|| Err(io::Error::from(io::ErrorKind::InvalidData)))
+            .map(DynamicImage::ImageRgba8);
+    }
+
+    match webp::still(&contents)? {
+        Some(still) => Ok(DynamicImage::ImageRgba8(still)),
+        None => load_raster(Cursor::new(contents), ImageFormat::WEBP)
+    }
+}
+
+#[cfg(feature = "svg")]
 /// Loads vector graphics to an `Image`.
-fn load_vector<R: Read + Seek>(mut read: R) -> io::Result<Tree> {
+fn load_vector<R: Read + Seek>(
+    mut read: R,
+    opts: &usvg::Options,
+    limits: SvgLimits
+) -> io::Result<Tree> {
+    let len = read.seek(SeekFrom::End(0))?;
+    if len > limits.max_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the svg document exceeds the maximum size of {} bytes", limits.max_bytes)
+        ));
+    }
+
+    let contents = read_to_vec(read)?;
+
+    // Count the element tags without parsing the document: this is an upper
+    // bound on the number of nodes usvg will have to process
+    let nodes = contents.windows(2)
+        .filter(|w| w[0] == b'<' && !b"/!?".contains(&w[1]))
+        .count();
+
+    if nodes > limits.max_nodes {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the svg document exceeds the maximum of {} nodes", limits.max_nodes)
+        ));
+    }
+
+    Tree::from_data(contents.as_ref(), opts).map_err(usvg_err_to_io)
+}
+
+/// Reads the contents of a stream to a buffer.
+fn read_to_vec<R: Read + Seek>(mut read: R) -> io::Result<Vec<u8>> {
     // Combute the length of the file and return to the start of
     // the stream.
     let len = read.seek(SeekFrom::End(0))?;
@@ -225,14 +811,19 @@ fn load_vector<R: Read + Seek>(mut read: R) -> io::Result<Tree> {
     let mut contents = Vec::with_capacity(len as usize);
     read.read_to_end(&mut contents)?;
 
-    match Tree::from_data(contents.as_ref(), &usvg::Options::default()) {
-        Ok(img) => Ok(img),
-        Err(usvg::Error::InvalidFileSuffix) => {
-            Err(io::Error::from(io::ErrorKind::InvalidInput))
+    Ok(contents)
+}
+
+#[cfg(feature = "svg")]
+/// Converts an `usvg::Error` to an `io::Error`.
+fn usvg_err_to_io(err: usvg::Error) -> io::Error {
+    match err {
+        usvg::Error::InvalidFileSuffix => {
+            io::Error::from(io::ErrorKind::InvalidInput)
         }
-        Err(usvg::Error::FileOpenFailed) => {
-            Err(io::Error::from(io::ErrorKind::Other))
+        usvg::Error::FileOpenFailed => {
+            io::Error::from(io::ErrorKind::Other)
         },
-        _ => Err(io::Error::from(io::ErrorKind::InvalidData)),
+        _ => io::Error::from(io::ErrorKind::InvalidData),
     }
 }
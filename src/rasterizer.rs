@@ -0,0 +1,84 @@
+use crate::{resample::{self, FitMode, ResampleError}, Image};
+#[cfg(feature = "svg")]
+use crate::{usvg::Tree, Svg};
+use image::{DynamicImage, FilterType};
+use std::io;
+
+/// An `Image` prepared for repeated rasterization.
+///
+/// `Svg` only stores the source of a document, so `Image::rasterize` parses
+/// a new `Tree` on every call. A `Rasterizer` parses it once, when it's
+/// created by `Image::rasterizer`, and renders every size from that tree.
+/// _Raster graphics_ are simply borrowed.
+///
+/// Parsed trees can't be shared between threads, so neither can a
+/// `Rasterizer`: create one per thread instead.
+pub struct Rasterizer<'a> {
+    source: Source<'a>
+}
+
+enum Source<'a> {
+    Raster(&'a DynamicImage),
+    #[cfg(feature = "svg")]
+    Svg(&'a Svg, Tree),
+}
+
+impl<'a> Rasterizer<'a> {
+    /// Creates a `Rasterizer` for `image`, parsing it's document if it is
+    /// _vector graphics_.
+    pub(crate) fn new(image: &'a Image) -> io::Result<Self> {
+        let source = match image {
+            Image::Raster(ras) => Source::Raster(ras),
+            #[cfg(feature = "svg")]
+            Image::Svg(svg) => Source::Svg(svg, svg.tree()?),
+        };
+
+        Ok(Rasterizer { source })
+    }
+
+    /// Rasterizes the image to a `DynamicImage`.
+    ///
+    /// Equivalent to `Image::rasterize`, except that the document of _vector
+    /// graphics_ is not parsed again.
+    pub fn rasterize<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+        &self,
+        filter: F,
+        size: (u32, u32),
+    ) -> Result<DynamicImage, ResampleError> {
+        match &self.source {
+            Source::Raster(ras) => resample::apply(filter, ras, size),
+            #[cfg(feature = "svg")]
+            Source::Svg(svg, tree) => resample::svg_with_options(tree, size, svg.options()),
+        }
+    }
+
+    /// Rasterizes the image to a `DynamicImage` of exactly the dimensions
+    /// specified by `size`, stretching it if needed.
+    ///
+    /// Equivalent to `Image::rasterize_exact`, except that the document of
+    /// _vector graphics_ is not parsed again.
+    pub fn rasterize_exact(
+        &self,
+        filter: FilterType,
+        size: (u32, u32),
+    ) -> Result<DynamicImage, ResampleError> {
+        let stretch = |src: &DynamicImage, size| resample::fit(src, size, FitMode::Stretch, filter);
+
+        match &self.source {
+            Source::Raster(ras) => resample::apply(stretch, ras, size),
+            #[cfg(feature = "svg")]
+            Source::Svg(svg, tree) => {
+                // The document is rendered onto a canvas of it's intrinsic
+                // size rather than of the size of it's view box, so that's
+                // the aspect ratio that has to be preserved
+                let canvas = tree.svg_node().size;
+                let (w, h) = (canvas.width(), canvas.height());
+                let scale = (size.0 as f64 / w).max(size.1 as f64 / h);
+                let cover = ((w * scale).ceil() as u32, (h * scale).ceil() as u32);
+
+                let rendered = resample::svg_with_options(tree, cover, svg.options())?;
+                resample::apply(stretch, &rendered, size)
+            }
+        }
+    }
+}
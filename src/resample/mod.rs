@@ -1,8 +1,10 @@
 //! A collection of commonly used resampling filters.
 
-use std::io;
-use image::{imageops, DynamicImage, ImageBuffer, GenericImageView, FilterType, Bgra};
-use resvg::{usvg::{self, Tree}, raqote::DrawTarget , FitTo};
+use crate::Background;
+use std::{convert::TryFrom, io, str::FromStr};
+use image::{imageops, DynamicImage, ImageBuffer, GenericImageView, FilterType, Luma, Rgba, RgbaImage};
+#[cfg(feature = "svg")]
+use resvg::{usvg::{self, Tree}, raqote::DrawTarget, FitTo};
 pub use error::ResampleError;
 
 mod error;
@@ -12,11 +14,88 @@ pub fn linear(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImag
     overfit(&scale(source, size, FilterType::Triangle)?, size)
 }
 
+/// [Linear resampling filter](https://en.wikipedia.org/wiki/Linear_interpolation)
+/// applied in [linear light](https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)).
+/// 
+/// Resampling directly in _sRGB_ space darkens high-contrast edges of
+/// downscaled images. This filter converts `source` to linear light before
+/// resampling it and back to _sRGB_ afterwards, at the cost of some extra
+/// computation. The alpha channel is not gamma-encoded and is resampled
+/// as is.
+pub fn linear_srgb(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
+    let (nw, nh) = fit_dimensions(source.dimensions(), size);
+    let source = source.to_rgba();
+
+    let linear = ImageBuffer::from_fn(source.width(), source.height(), |x, y| {
+        let px = source.get_pixel(x, y);
+        Rgba([
+            srgb_to_linear(px[0]),
+            srgb_to_linear(px[1]),
+            srgb_to_linear(px[2]),
+            px[3] as f32 / 255.0
+        ])
+    });
+
+    let scaled: ImageBuffer<Rgba<f32>, Vec<f32>> = imageops::resize(
+        &linear, nw, nh, FilterType::Triangle
+    );
+
+    let output = RgbaImage::from_fn(nw, nh, |x, y| {
+        let px = scaled.get_pixel(x, y);
+        Rgba([
+            linear_to_srgb(px[0]),
+            linear_to_srgb(px[1]),
+            linear_to_srgb(px[2]),
+            (px[3] * 255.0).round().max(0.0).min(255.0) as u8
+        ])
+    });
+
+    overfit(&DynamicImage::ImageRgba8(output), size)
+}
+
 /// [Lanczos resampling filter](https://en.wikipedia.org/wiki/Lanczos_resampling).
 pub fn cubic(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
     overfit(&scale(source, size, FilterType::Lanczos3)?, size)
 }
 
+/// [Mitchell–Netravali resampling filter](https://en.wikipedia.org/wiki/Mitchell%E2%80%93Netravali_filters).
+/// 
+/// Uses the `B = C = 1/3` parameters recommended by Mitchell and Netravali,
+/// which produce noticeably less ringing around hard edges than `cubic` at
+/// the cost of slightly softer results. A good middle-ground for
+/// downscaled logos.
+pub fn mitchell(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
+    let scaled = resize_with_kernel(
+        &source.to_rgba(),
+        fit_dimensions(source.dimensions(), size),
+        mitchell_kernel,
+        2.0
+    );
+
+    overfit(&DynamicImage::ImageRgba8(scaled), size)
+}
+
+/// [Gaussian](https://en.wikipedia.org/wiki/Gaussian_blur)-prefiltered
+/// [linear resampling filter](https://en.wikipedia.org/wiki/Linear_interpolation).
+/// 
+/// Blurs `source` before downscaling it to suppress the aliasing (moiré
+/// patterns) plain linear resampling produces on heavily downscaled
+/// high-frequency content, such as photographic favicons. The standard
+/// deviation of the blur is `(ratio - 1) / 2`, where `ratio` is the
+/// downscale ratio, so upscaling skips the blur entirely.
+pub fn gaussian(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
+    let (nw, nh) = fit_dimensions(source.dimensions(), size);
+    let ratio = (source.width() as f32 / nw as f32).max(source.height() as f32 / nh as f32);
+    let sigma = (ratio - 1.0) / 2.0;
+
+    if sigma > 0.0 {
+        let blurred = DynamicImage::ImageRgba8(imageops::blur(source, sigma));
+        overfit(&scale(&blurred, size, FilterType::Triangle)?, size)
+    } else {
+        linear(source, size)
+    }
+}
+
 /// [Nearest-Neighbor resampling filter](https://en.wikipedia.org/wiki/Nearest-neighbor_interpolation).
 pub fn nearest(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
     let scaled = if source.width() < size.0 && source.height() < size.1 {
@@ -28,6 +107,334 @@ pub fn nearest(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicIma
     overfit(&scaled, size)
 }
 
+/// Rescales `source` to the dimensions specified by `size` and thresholds
+/// it's alpha channel into a 1-bit monochrome mask, such as the _AND_ mask
+/// of legacy cursors.
+/// 
+/// The output is a `DynamicImage::ImageLuma8` in which pixels whose alpha
+/// is above `threshold` are white (`255`, opaque) and all other pixels are
+/// black (`0`, transparent). Note that the _AND_ mask of `.ico` and `.cur`
+/// files uses the opposite convention.
+pub fn mono_mask(
+    source: &DynamicImage,
+    size: (u32, u32),
+    threshold: u8
+) -> io::Result<DynamicImage> {
+    let scaled = linear(source, size)?.to_rgba();
+    let mask = ImageBuffer::from_fn(size.0, size.1, |x, y| {
+        Luma([if scaled.get_pixel(x, y)[3] > threshold { 255 } else { 0 }])
+    });
+
+    Ok(DynamicImage::ImageLuma8(mask))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The built-in resampling filters.
+/// 
+/// Unlike bare function pointers, a `ResampleFilter` can be stored in
+/// configuration or parsed from a command-line flag via it's `FromStr`
+/// implementation.
+pub enum ResampleFilter {
+    /// See `resample::nearest`.
+    Nearest,
+    /// See `resample::linear`.
+    Linear,
+    /// See `resample::cubic`.
+    Cubic,
+    /// See `resample::mitchell`.
+    Mitchell,
+    /// See `resample::gaussian`.
+    Gaussian,
+    /// See `resample::linear_srgb`.
+    LinearSrgb,
+}
+
+impl ResampleFilter {
+    /// Applies the resampling filter to `source`.
+    /// 
+    /// Use a closure such as `|src, size| filter.apply(src, size)` to pass
+    /// it as the `filter` argument of `Image::rasterize` or
+    /// `Encode::add_icon`.
+    pub fn apply(&self, source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
+        match self {
+            Self::Nearest    => nearest(source, size),
+            Self::Linear     => linear(source, size),
+            Self::Cubic      => cubic(source, size),
+            Self::Mitchell   => mitchell(source, size),
+            Self::Gaussian   => gaussian(source, size),
+            Self::LinearSrgb => linear_srgb(source, size),
+        }
+    }
+}
+
+impl FromStr for ResampleFilter {
+    type Err = io::Error;
+
+    /// Parses the name of a resampling filter, ignoring case.
+    /// 
+    /// The accepted names are `nearest`, `linear`, `cubic`, `mitchell`,
+    /// `gaussian` and `linear-srgb`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const FILTERS: [(&str, ResampleFilter); 6] = [
+            ("nearest", ResampleFilter::Nearest),
+            ("linear", ResampleFilter::Linear),
+            ("cubic", ResampleFilter::Cubic),
+            ("mitchell", ResampleFilter::Mitchell),
+            ("gaussian", ResampleFilter::Gaussian),
+            ("linear-srgb", ResampleFilter::LinearSrgb),
+        ];
+
+        FILTERS.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, filter)| *filter)
+            .ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown resampling filter: {:?}", s)
+            ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Determines how an image is fitted into dimensions of a different aspect
+/// ratio by `resample::fit`.
+pub enum FitMode {
+    /// Scales the image to fit inside the target dimensions, padding the
+    /// remaining area with transparent pixels. This is what the built-in
+    /// resampling filters do.
+    Contain,
+    /// Scales the image to fill the target dimensions, cropping the overflow
+    /// around it's center.
+    Cover,
+    /// Scales the image to exactly the target dimensions, ignoring it's
+    /// aspect ratio.
+    Stretch,
+}
+
+/// Rescales `source` to the dimensions specified by `size` using `filter`,
+/// fitting it according to `mode`.
+/// 
+/// Returns `Err` of kind `io::ErrorKind::InvalidInput` if `source` is empty
+/// or covering `size` with it would overflow it's dimensions.
+/// 
+/// To use it as the resampling filter of an icon, wrap it in a closure: 
+/// `|src, size| resample::fit(src, size, FitMode::Cover, FilterType::Triangle)`.
+pub fn fit(
+    source: &DynamicImage,
+    size: (u32, u32),
+    mode: FitMode,
+    filter: FilterType
+) -> io::Result<DynamicImage> {
+    match mode {
+        FitMode::Contain => overfit(&scale(source, size, filter)?, size),
+        FitMode::Cover => {
+            let (w, h) = (source.width() as u64, source.height() as u64);
+            let (sw, sh) = (size.0 as u64, size.1 as u64);
+
+            // Round up so that the scaled image covers `size` entirely
+            let (nw, nh) = if w * sh > h * sw {
+                (div_round_up(w * sh, h), Some(size.1))
+            } else {
+                (Some(size.0), div_round_up(h * sw, w))
+            };
+
+            let (nw, nh) = match (nw, nh) {
+                (Some(nw), Some(nh)) => (nw, nh),
+                _ => return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{}x{} can't be scaled to cover {}x{}", w, h, sw, sh)
+                ))
+            };
+
+            let scaled = imageops::resize(source, nw, nh, filter);
+            overfit(&DynamicImage::ImageRgba8(scaled), size)
+        },
+        FitMode::Stretch => {
+            Ok(DynamicImage::ImageRgba8(imageops::resize(source, size.0, size.1, filter)))
+        }
+    }
+}
+
+/// Sharpens `source` using an [_unsharp mask_](https://en.wikipedia.org/wiki/Unsharp_masking).
+/// 
+/// The difference between `source` and a copy of it blurred with a
+/// standard deviation of `radius` pixels is scaled by `amount` and added
+/// back to `source`. The alpha channel is left unchanged.
+pub fn unsharp_mask(source: &DynamicImage, amount: f32, radius: f32) -> DynamicImage {
+    let mut output = source.to_rgba();
+    let blurred = imageops::blur(&output, radius);
+
+    for (px, blurred) in output.pixels_mut().zip(blurred.pixels()) {
+        for (c, b) in px.0.iter_mut().zip(blurred.0.iter()).take(3) {
+            let (c_f, b_f) = (*c as f32, *b as f32);
+            *c = (c_f + amount * (c_f - b_f)).round().max(0.0).min(255.0) as u8;
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+#[inline]
+/// Wraps a resampling filter so that the fully transparent borders of the
+/// source image are trimmed before it is resampled.
+/// 
+/// Equivalent to `resample::trimmed_with_threshold(filter, 0)`.
+pub fn trimmed<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+    filter: F
+) -> impl FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> {
+    trimmed_with_threshold(filter, 0)
+}
+
+/// Wraps a resampling filter so that the transparent borders of the source
+/// image are trimmed before it is resampled, so that it's content makes use
+/// of the whole resolution of the output.
+/// 
+/// Pixels whose alpha is at most `alpha_threshold` are considered
+/// transparent. Fully transparent images are resampled as is.
+pub fn trimmed_with_threshold<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+    mut filter: F,
+    alpha_threshold: u8
+) -> impl FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> {
+    move |source, size| match content_bounds(source, alpha_threshold) {
+        Some((x, y, w, h)) => {
+            let cropped = DynamicImage::ImageRgba8(source.view(x, y, w, h).to_image());
+            filter(&cropped, size)
+        },
+        None => filter(source, size)
+    }
+}
+
+/// Computes the bounding box of the pixels of `source` whose alpha is above
+/// `alpha_threshold` as `(x, y, width, height)`.
+/// 
+/// Returns `None` if there are no such pixels.
+pub(crate) fn content_bounds(
+    source: &DynamicImage,
+    alpha_threshold: u8
+) -> Option<(u32, u32, u32, u32)> {
+    let (x0, y0, x1, y1) = source.pixels()
+        .filter(|(_, _, px)| px[3] > alpha_threshold)
+        .fold((std::u32::MAX, std::u32::MAX, 0, 0), |(x0, y0, x1, y1), (x, y, _)| {
+            (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        });
+
+    if x0 > x1 {
+        None
+    } else {
+        Some((x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+    }
+}
+
+/// Wraps a resampling filter so that it's output is sharpened with an
+/// _unsharp mask_ of radius `1.0` and strength `amount`.
+/// 
+/// See `resample::unsharp_mask` for more information.
+pub fn sharpened<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+    mut filter: F,
+    amount: f32
+) -> impl FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> {
+    move |source, size| Ok(unsharp_mask(&filter(source, size)?, amount, 1.0))
+}
+
+/// Wraps a resampling filter so that it's output is composited over a solid
+/// background of color `background`, including the borders added to fit
+/// the requested dimensions.
+/// 
+/// The transparent default of the built-in filters is kept when not using
+/// this combinator.
+pub fn on_background<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+    mut filter: F,
+    background: impl Into<Rgba<u8>>
+) -> impl FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> {
+    let background = background.into();
+    move |source, size| overfit_with(&filter(source, size)?, size, background)
+}
+
+/// Wraps a resampling filter so that it's output is inset by `fraction`
+/// of the requested dimensions on every side, leaving a transparent safe
+/// zone around the content.
+/// 
+/// The source is resampled to `size * (1 - 2 * fraction)` and centered on
+/// a transparent canvas of the dimensions specified by `size`, which is the
+/// layout expected by maskable and adaptive icons (typically with a
+/// `fraction` of `0.1`).
+/// 
+/// The wrapped filter returns `Err(io::Error::from(io::ErrorKind::InvalidInput))`
+/// if `fraction` is not in the range `0.0..0.5`.
+pub fn padded<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+    mut filter: F,
+    fraction: f32
+) -> impl FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> {
+    move |source, size| {
+        if !(0.0..0.5).contains(&fraction) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the padding fraction must be in the range 0.0..0.5, got {}", fraction)
+            ));
+        }
+
+        let inset = |len: u32| ((len as f32 * (1.0 - 2.0 * fraction)).round() as u32).max(1);
+        overfit(&filter(source, (inset(size.0), inset(size.1)))?, size)
+    }
+}
+
+/// Masks the corners of `source` so that it is clipped to a rounded
+/// rectangle with corners of radius `radius` pixels.
+/// 
+/// The edges of the mask are anti-aliased and the alpha of `source` is
+/// multiplied by the coverage of each pixel. The radius is clamped to half
+/// of the shorter side of `source`, and `source` is returned unchanged if
+/// it is not positive.
+pub fn round_corners(source: &DynamicImage, radius: f32) -> DynamicImage {
+    if radius.is_nan() || radius <= 0.0 {
+        return source.clone();
+    }
+
+    let (w, h) = source.dimensions();
+    let radius = radius.min(w.min(h) as f32 / 2.0);
+    let mut output = source.to_rgba();
+
+    for (x, y, px) in output.enumerate_pixels_mut() {
+        // Distance from the center of the pixel to the center of the
+        // nearest corner circle, along each axis
+        let ex = (x as f32 + 0.5).min(w as f32 - x as f32 - 0.5);
+        let ey = (y as f32 + 0.5).min(h as f32 - y as f32 - 0.5);
+        let (dx, dy) = (radius - ex, radius - ey);
+
+        // Only the corner squares are masked, the rest of the image is
+        // fully covered
+        if dx > 0.0 && dy > 0.0 {
+            let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).max(0.0).min(1.0);
+            px[3] = (px[3] as f32 * coverage).round() as u8;
+        }
+    }
+
+    DynamicImage::ImageRgba8(output)
+}
+
+/// Wraps a resampling filter so that it's output is clipped to a rounded
+/// rectangle with corners of radius `radius`, in pixels of the output.
+/// 
+/// See `resample::round_corners` for more information.
+pub fn rounded<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+    mut filter: F,
+    radius: f32
+) -> impl FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> {
+    move |source, size| Ok(round_corners(&filter(source, size)?, radius))
+}
+
+/// Wraps a resampling filter so that it's output is clipped to a circle.
+/// 
+/// Equivalent to `resample::rounded` with a radius of half the shorter side
+/// of the output: non-square outputs are clipped to a rectangle with fully
+/// rounded ends.
+pub fn circle<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+    mut filter: F
+) -> impl FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage> {
+    move |source, size| {
+        Ok(round_corners(&filter(source, size)?, size.0.min(size.1) as f32 / 2.0))
+    }
+}
+
 /// Aplies a resampling filter to `source` and checks if the dimensions
 /// of the output match the ones specified by `size`.
 pub fn apply<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
@@ -49,41 +456,269 @@ pub fn apply<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
 fn nearest_upscale_integer(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
     let (w, h) = source.dimensions();
 
-    let scale = if w > h { size.0 / w } else { size.1 / h };
+    // Guard against a zero scale factor in case this is ever called
+    // with a source larger than `size`
+    let scale = (if w > h { size.0 / w } else { size.1 / h }).max(1);
     let (nw, nh) = (w * scale, h * scale);
 
     Ok(DynamicImage::ImageRgba8(imageops::resize(source, nw, nh, FilterType::Nearest)))
 }
 
 /// Rescales `source` to fit the dimensions specified by `size`.
+/// 
+/// The dimension that does not fill `size` is rounded down, but never to
+/// less than one pixel, so that extremely wide or tall sources still
+/// produce a valid image.
 fn scale(source: &DynamicImage, size: (u32, u32), filter: FilterType) -> io::Result<DynamicImage> {
-    let (w, h) = source.dimensions();
-    let (nw, nh) = if w > h { (size.0, size.0 * h / w)} else { (size.1 * w / h, size.1) };
+    let (nw, nh) = fit_dimensions(source.dimensions(), size);
 
     Ok(DynamicImage::ImageRgba8(imageops::resize(source, nw, nh, filter)))
 }
 
+/// Converts an _sRGB_-encoded channel to linear light.
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a channel in linear light to _sRGB_.
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+
+    (c * 255.0).round().max(0.0).min(255.0) as u8
+}
+
+/// Computes the dimensions of an image of dimensions `(w, h)` rescaled to
+/// fit the dimensions specified by `size`.
+fn fit_dimensions((w, h): (u32, u32), size: (u32, u32)) -> (u32, u32) {
+    let (nw, nh) = if w > h { (size.0, size.0 * h / w)} else { (size.1 * w / h, size.1) };
+    (nw.max(1), nh.max(1))
+}
+
+/// The [Mitchell–Netravali](https://en.wikipedia.org/wiki/Mitchell%E2%80%93Netravali_filters)
+/// kernel with `B = C = 1/3`.
+/// Divides `n` by `d`, rounding up. Returns `None` if `d` is zero or the
+/// quotient doesn't fit in a `u32`.
+fn div_round_up(n: u64, d: u64) -> Option<u32> {
+    let quotient = n.checked_add(d.checked_sub(1)?)? / d;
+    u32::try_from(quotient).ok()
+}
+
+fn mitchell_kernel(x: f32) -> f32 {
+    const B: f32 = 1.0 / 3.0;
+    const C: f32 = 1.0 / 3.0;
+
+    let x = x.abs();
+    let k = if x < 1.0 {
+        (12.0 - 9.0 * B - 6.0 * C) * x.powi(3)
+            + (-18.0 + 12.0 * B + 6.0 * C) * x.powi(2)
+            + (6.0 - 2.0 * B)
+    } else if x < 2.0 {
+        (-B - 6.0 * C) * x.powi(3)
+            + (6.0 * B + 30.0 * C) * x.powi(2)
+            + (-12.0 * B - 48.0 * C) * x
+            + (8.0 * B + 24.0 * C)
+    } else {
+        0.0
+    };
+
+    k / 6.0
+}
+
+/// Rescales `source` to the dimensions `(nw, nh)` by convolving it with
+/// `kernel`, first horizontally and then vertically.
+fn resize_with_kernel<K: Fn(f32) -> f32>(
+    source: &RgbaImage,
+    (nw, nh): (u32, u32),
+    kernel: K,
+    support: f32
+) -> RgbaImage {
+    let (w, h) = source.dimensions();
+
+    // Keep the intermediate result in floating point to avoid
+    // quantizing it twice
+    let mut rows = vec![[0.0f32; 4]; (nw * h) as usize];
+    for (x, (start, weights)) in kernel_weights(w, nw, &kernel, support).iter().enumerate() {
+        for y in 0..h {
+            let acc = &mut rows[(y * nw) as usize + x];
+
+            for (i, weight) in weights.iter().enumerate() {
+                let px = source.get_pixel(start + i as u32, y);
+                for (a, c) in acc.iter_mut().zip(px.0.iter()) {
+                    *a += *c as f32 * weight;
+                }
+            }
+        }
+    }
+
+    let mut output = RgbaImage::new(nw, nh);
+    for (y, (start, weights)) in kernel_weights(h, nh, &kernel, support).iter().enumerate() {
+        for x in 0..nw {
+            let mut acc = [0.0f32; 4];
+
+            for (i, weight) in weights.iter().enumerate() {
+                let px = rows[((start + i as u32) * nw + x) as usize];
+                for (a, c) in acc.iter_mut().zip(px.iter()) {
+                    *a += c * weight;
+                }
+            }
+
+            let px = output.get_pixel_mut(x, y as u32);
+            for (c, a) in px.0.iter_mut().zip(acc.iter()) {
+                *c = a.round().max(0.0).min(255.0) as u8;
+            }
+        }
+    }
+
+    output
+}
+
+/// Computes, for each pixel of an axis rescaled from `len` to `new_len`
+/// pixels, the index of the first source pixel that contributes to it
+/// alongside the normalized weights of the contributing pixels.
+fn kernel_weights<K: Fn(f32) -> f32>(
+    len: u32,
+    new_len: u32,
+    kernel: &K,
+    support: f32
+) -> Vec<(u32, Vec<f32>)> {
+    let ratio = len as f32 / new_len as f32;
+
+    // When downscaling the kernel is stretched to cover every source pixel
+    let stretch = ratio.max(1.0);
+    let radius = support * stretch;
+
+    (0..new_len).map(|i| {
+        let center = (i as f32 + 0.5) * ratio;
+        let left = (center - radius).floor().max(0.0) as u32;
+        let right = ((center + radius).ceil() as u32).min(len);
+
+        let mut weights: Vec<f32> = (left..right)
+            .map(|j| kernel((j as f32 + 0.5 - center) / stretch))
+            .collect();
+
+        let sum: f32 = weights.iter().sum();
+        if sum != 0.0 {
+            weights.iter_mut().for_each(|w| *w /= sum);
+        }
+
+        (left, weights)
+    }).collect()
+}
+
+#[inline]
 /// Adds transparent borders to an image so that the output is square.
-fn overfit(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
-    let mut output = DynamicImage::new_rgba8(size.0, size.1);
+/// 
+/// Images larger than `size` in either dimension are cropped around their
+/// center.
+pub(crate) fn overfit(source: &DynamicImage, size: (u32, u32)) -> io::Result<DynamicImage> {
+    overfit_with(source, size, Background::TRANSPARENT)
+}
+
+/// Centers `source` on a canvas of the dimensions specified by `size` filled
+/// with the color `background`.
+/// 
+/// Transparent areas of `source` are composited over the background, which
+/// makes this usefull for outputs that have no alpha channel, such as _BMP_
+/// and _JPEG_ files. Images larger than `size` in either dimension are
+/// cropped around their center.
+/// 
+/// When the difference between the dimensions of `source` and `size` is
+/// odd, the extra pixel is always placed on the right or bottom border, so
+/// that content is biased towards the top-left corner.
+pub fn overfit_with(
+    source: &DynamicImage,
+    size: (u32, u32),
+    background: impl Into<Rgba<u8>>
+) -> io::Result<DynamicImage> {
+    let mut output = DynamicImage::ImageRgba8(
+        RgbaImage::from_pixel(size.0, size.1, background.into())
+    );
 
-    let dx = (output.width()  - source.width() ) / 2;
-    let dy = (output.height() - source.height()) / 2;
+    let (w, h) = (source.width().min(size.0), source.height().min(size.1));
+    let (sx, sy) = ((source.width()  - w) / 2, (source.height() - h) / 2);
+    let (dx, dy) = ((size.0 - w) / 2, (size.1 - h) / 2);
 
-    imageops::overlay(&mut output, source, dx, dy);
+    imageops::overlay(&mut output, &source.view(sx, sy, w, h), dx, dy);
     Ok(output)
 }
 
+#[cfg(feature = "svg")]
+#[inline]
 /// Rasterizes an _SVG_ tree to a `DynamicImage`.
 pub(crate) fn svg(source: &Tree, size: (u32, u32)) -> Result<DynamicImage, ResampleError> {
-    let rect = source.svg_node().view_box.rect;
-    let (w, h) = (rect.width(), rect.height());
+    render_svg(source, size, None, &usvg::Options::default())
+}
+
+#[cfg(feature = "svg")]
+/// Rasterizes an _SVG_ tree to a `DynamicImage`, rendering it on top of a
+/// solid background of color `background`.
+/// 
+/// The whole output is filled, including the borders added to fit `size`.
+/// Opaque backgrounds are filled by _resvg_ itself before rendering, which
+/// is more accurate than compositing the rasterized image for certain blend
+/// modes. Translucent ones are composited afterwards.
+pub fn svg_on_background(
+    source: &Tree,
+    size: (u32, u32),
+    background: impl Into<Rgba<u8>>
+) -> Result<DynamicImage, ResampleError> {
+    let background = background.into();
+    let fill = match background {
+        Rgba([r, g, b, 255]) => Some(usvg::Color::new(r, g, b)),
+        _ => None
+    };
+
+    let rendered = render_svg(source, size, fill, &usvg::Options::default())?;
+    Ok(overfit_with(&rendered, size, background)?)
+}
+
+#[cfg(feature = "svg")]
+#[inline]
+/// Rasterizes an _SVG_ tree to a `DynamicImage`, rendering it with the
+/// options specified by `opts`.
+/// 
+/// Lengths in physical units (such as `mm` or `pt`) are resolved when the
+/// tree is parsed, so the same `opts` should be used to load the tree with
+/// `Image::load_vector_with`. At render time `opts.dpi` and the other
+/// options only affect images nested in the document.
+pub fn svg_with_options(
+    source: &Tree,
+    size: (u32, u32),
+    opts: &usvg::Options
+) -> Result<DynamicImage, ResampleError> {
+    render_svg(source, size, None, opts)
+}
+
+#[cfg(feature = "svg")]
+/// Rasterizes an _SVG_ tree to a `DynamicImage`, optionally filling the
+/// background with a solid color.
+fn render_svg(
+    source: &Tree,
+    size: (u32, u32),
+    background: Option<usvg::Color>,
+    opts: &usvg::Options
+) -> Result<DynamicImage, ResampleError> {
+    // resvg maps the view box (including it's origin and the
+    // preserveAspectRatio attribute) onto a canvas of the intrinsic size
+    // of the document, so it's the canvas that has to be fitted to `size`
+    let canvas = source.svg_node().size;
+    let (w, h) = (canvas.width(), canvas.height());
     let fit_to = if w > h { FitTo::Width(size.0) } else { FitTo::Height(size.1) };
 
     let opts = resvg::Options {
-        usvg: usvg::Options::default(),
+        usvg: opts.clone(),
         fit_to,
-        background: None
+        background
     };
 
     // In this context it's safe to assume render_to_image will return Some(_)
@@ -94,16 +729,68 @@ pub(crate) fn svg(source: &Tree, size: (u32, u32)) -> Result<DynamicImage, Resam
     Ok(draw_target_to_rgba(draw_target, size)?)
 }
 
-#[inline]
+#[cfg(feature = "svg")]
+/// Rasterizes an _SVG_ tree to a `DynamicImage` using
+/// [_supersampling_](https://en.wikipedia.org/wiki/Supersampling).
+/// 
+/// The tree is rendered at `factor` times the dimensions specified by `size`
+/// and then downscaled to `size`, which gives cleaner _anti-aliasing_ than
+/// rendering at the target dimensions directly. A `factor` of `0` is treated
+/// as `1`.
+/// 
+/// Returns `Err(ResampleError::Io(_))` of kind `io::ErrorKind::InvalidInput`
+/// if the supersampled dimensions overflow.
+pub fn svg_supersampled(
+    source: &Tree,
+    size: (u32, u32),
+    factor: u32
+) -> Result<DynamicImage, ResampleError> {
+    let factor = factor.max(1);
+    let supersampled = match (size.0.checked_mul(factor), size.1.checked_mul(factor)) {
+        (Some(w), Some(h)) => (w, h),
+        _ => return Err(ResampleError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}x{} supersampled by {} overflows", size.0, size.1, factor)
+        )))
+    };
+
+    let rendered = svg(source, supersampled)?;
+
+    // A triangle filter averages the samples without the ringing
+    // artifacts Lanczos would introduce around hard edges
+    Ok(DynamicImage::ImageRgba8(
+        imageops::resize(&rendered, size.0, size.1, FilterType::Triangle)
+    ))
+}
+
+#[cfg(feature = "svg")]
 /// Converts a `DrawTarget` to a `DynamicImage`.
-fn draw_target_to_rgba(mut surface: DrawTarget, size: (u32, u32)) -> io::Result<DynamicImage> {
+fn draw_target_to_rgba(surface: DrawTarget, size: (u32, u32)) -> io::Result<DynamicImage> {
     let (w, h) = (surface.width() as u32, surface.height() as u32);
-    let data = surface.get_data_u8_mut().to_vec();
+
+    // raqote stores pixels as premultiplied ARGB words, so the channels
+    // are extracted by value (which is independent of the platform's
+    // endianness) and un-premultiplied
+    let mut data = Vec::with_capacity(4 * surface.get_data().len());
+    for &px in surface.get_data() {
+        let a = px >> 24;
+        let unpremultiply = |c: u32| match a {
+            0 => 0,
+            _ => (((c & 0xff) * 255 + a / 2) / a).min(255) as u8
+        };
+
+        data.extend_from_slice(&[
+            unpremultiply(px >> 16),
+            unpremultiply(px >> 8),
+            unpremultiply(px),
+            a as u8
+        ]);
+    }
 
     // If ImageBuffer::from_vec returns None then there's a bug in
     // resvg
-    match ImageBuffer::<Bgra<u8>, Vec<u8>>::from_vec(w, h, data) {
-        Some(buf) => overfit(&DynamicImage::ImageBgra8(buf), size),
+    match ImageBuffer::<Rgba<u8>, Vec<u8>>::from_vec(w, h, data) {
+        Some(buf) => overfit(&DynamicImage::ImageRgba8(buf), size),
         None      => panic!("Buffer in not big enought")
     }
-}
\ No newline at end of file
+}
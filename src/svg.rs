@@ -0,0 +1,84 @@
+use crate::{encode::XML_OPTS, usvg::{self, Tree}, usvg_err_to_io};
+use std::{convert::From, io, sync::Arc};
+
+#[derive(Clone)]
+/// Thread-safe storage for _vector graphics_.
+/// 
+/// `usvg::Tree` is built out of reference-counted nodes, so it can't be
+/// safely shared between threads. `Svg` instead stores the document in the
+/// normalized form produced by `usvg`, alongside the options it was parsed
+/// with, and parses a new `Tree` whenever one is needed. Clones share the
+/// same document.
+pub struct Svg {
+    source: Arc<str>,
+    opts: Arc<usvg::Options>,
+    view_box: (f64, f64),
+}
+
+impl Svg {
+    /// Creates an `Svg` from a `Tree` parsed with the options specified by
+    /// `opts`.
+    /// 
+    /// The options are used whenever the document is parsed or rendered
+    /// again, so that relative image references are still resolved against
+    /// `opts.path`.
+    pub fn with_options(tree: &Tree, opts: &usvg::Options) -> Self {
+        let rect = tree.svg_node().view_box.rect;
+
+        Svg {
+            source: Arc::from(tree.to_string(XML_OPTS)),
+            opts: Arc::new(opts.clone()),
+            view_box: (rect.width(), rect.height()),
+        }
+    }
+
+    /// Parses a new `Tree` from the document, using the options it was
+    /// originally parsed with.
+    /// 
+    /// # Return Value
+    /// 
+    /// * Returns `Ok(tree)` if the document could be parsed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::Other))` if the tree allocation failed.
+    /// * Returns `Err(io::Error::from(io::ErrorKind::InvalidData))` otherwise.
+    pub fn tree(&self) -> io::Result<Tree> {
+        Tree::from_str(&self.source, &self.opts).map_err(usvg_err_to_io)
+    }
+
+    #[inline]
+    /// Returns the options the document was parsed with.
+    pub fn options(&self) -> &usvg::Options {
+        &self.opts
+    }
+
+    #[inline]
+    /// Returns the document as an _SVG_ string.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    #[inline]
+    /// Returns the width of the view box of the document.
+    pub fn width(&self) -> f64 {
+        self.view_box.0
+    }
+
+    #[inline]
+    /// Returns the height of the view box of the document.
+    pub fn height(&self) -> f64 {
+        self.view_box.1
+    }
+}
+
+impl From<&Tree> for Svg {
+    #[inline]
+    fn from(tree: &Tree) -> Self {
+        Svg::with_options(tree, &usvg::Options::default())
+    }
+}
+
+impl From<Tree> for Svg {
+    #[inline]
+    fn from(tree: Tree) -> Self {
+        Svg::from(&tree)
+    }
+}
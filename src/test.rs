@@ -1,5 +1,20 @@
-use crate::{encode, resample, Image};
-use std::{io, fs::File};
+use crate::{
+    decode::{self, Decode, DecodingError},
+    encode::{self, Encode, EncodingError, Save},
+    png,
+    resample::{self, FitMode, ResampleFilter},
+    Background,
+    FormatHandler,
+    Icon,
+    Image,
+    StdSize
+};
+#[cfg(feature = "svg")]
+use crate::{usvg, Svg, SvgLimits};
+use image::{
+    DynamicImage, FilterType, GenericImageView, ImageFormat, ImageOutputFormat, Rgba, RgbaImage
+};
+use std::{io::{self, BufReader, Cursor, Read, Seek}, fs::File, iter};
 
 #[test]
 fn load() -> io::Result<()> {
@@ -35,6 +50,7 @@ fn rasterize() -> io::Result<()> {
     let mut file_near = File::create("tests/rasterize/near.png").expect("Couldn't create file");
     let mut file_linear = File::create("tests/rasterize/linear.png").expect("Couldn't create file");
     let mut file_cubic = File::create("tests/rasterize/cubic.png").expect("Couldn't create file");
+    let mut file_mitchell = File::create("tests/rasterize/mitchell.png").expect("Couldn't create file");
     let mut file_svg = File::create("tests/rasterize/svg.png").expect("Couldn't create file");
 
     let source_png = Image::open("tests/test.png").expect("File not found");
@@ -55,8 +71,1491 @@ fn rasterize() -> io::Result<()> {
         &mut file_cubic
     )?;
 
+    encode::png(
+        &source_png.rasterize(resample::mitchell, (32, 32)).expect("Failed"),
+        &mut file_mitchell
+    )?;
+
     encode::png(
         &source_svg.rasterize(resample::nearest, (32, 32)).expect("Failed"),
         &mut file_svg
     )
 }
+
+#[test]
+fn accessors() {
+    let raster = Image::from(DynamicImage::new_rgba8(16, 16));
+
+    assert!(raster.is_raster());
+    assert!(!raster.is_svg());
+    assert!(raster.as_raster().is_some());
+    #[cfg(feature = "svg")]
+    assert!(raster.as_svg().is_none());
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_supersampled() {
+    let svg = usvg::Tree::from_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32">
+            <path d="M 3 5 L 29 9 L 12 28 Z"/>
+        </svg>"#,
+        &usvg::Options::default()
+    ).expect("Failed to parse svg");
+
+    let direct = Image::from(svg.clone()).rasterize(resample::nearest, (32, 32))
+        .expect("Failed");
+    let supersampled = resample::svg_supersampled(&svg, (32, 32), 2)
+        .expect("Failed");
+    let reference = resample::svg_supersampled(&svg, (32, 32), 16)
+        .expect("Failed");
+
+    assert_eq!(supersampled.dimensions(), (32, 32));
+
+    // Supersampled edges should be closer to a high-quality reference
+    let error = |img: &DynamicImage| img.to_rgba()
+        .pixels()
+        .zip(reference.to_rgba().pixels())
+        .map(|(a, b)| (a[3] as i32 - b[3] as i32).abs())
+        .sum::<i32>();
+
+    assert!(error(&supersampled) < error(&direct));
+
+    match resample::svg_supersampled(&svg, (32, 32), std::u32::MAX) {
+        Err(resample::ResampleError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+        _ => panic!("Supersampled past the range of u32")
+    }
+}
+
+#[test]
+fn load_with_format() -> io::Result<()> {
+    let mut buf = Vec::new();
+    encode::png(&DynamicImage::new_rgba8(16, 16), &mut buf)?;
+
+    let image = Image::load_with_format(Cursor::new(&buf), ImageFormat::PNG)?;
+    assert_eq!(image.dimensions(), (16.0, 16.0));
+
+    assert!(Image::load_with_format(Cursor::new(&buf), ImageFormat::BMP).is_err());
+    Ok(())
+}
+
+/// A minimal icon family holding a single _PNG_-encoded icon.
+struct Single {
+    icon: (u32, u32),
+    image: Image
+}
+
+impl<'a> Decode<'a> for Single {
+    type Icon = (u32, u32);
+    type Iter = iter::Once<(&'a (u32, u32), &'a Image)>;
+
+    fn read<R: Read + Seek>(r: R) -> Result<Self, DecodingError> {
+        let image = decode::png(&mut BufReader::new(r))?;
+
+        Ok(Single { icon: image.dimensions(), image: Image::from(image) })
+    }
+
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn get(&self, icon: &Self::Icon) -> Option<&Image> {
+        if self.icon == *icon { Some(&self.image) } else { None }
+    }
+
+    fn iter(&'a self) -> Self::Iter {
+        iter::once((&self.icon, &self.image))
+    }
+}
+
+#[test]
+fn read_icon() -> io::Result<()> {
+    let mut buf = Vec::new();
+    encode::png(&DynamicImage::new_rgba8(32, 32), &mut buf)?;
+
+    let found = Single::read_icon(Cursor::new(&buf), &(32, 32))?;
+    assert_eq!(found.map(|img| img.dimensions()), Some((32.0, 32.0)));
+
+    let missing = Single::read_icon(Cursor::new(&buf), &(16, 16))?;
+    assert!(missing.is_none());
+
+    let family = Single::read(Cursor::new(&buf))?;
+    assert!(family.contains_icon(&(32, 32)));
+    assert!(!family.contains_icon(&(16, 16)));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn from_svg_str() -> io::Result<()> {
+    let image = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 48 24"/>"#
+    )?;
+
+    assert!(image.is_svg());
+    assert_eq!(image.dimensions(), (48.0, 24.0));
+    assert!(Image::from_svg_str("not an svg").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn background() {
+    assert_eq!("#ff8800".parse::<Background>().ok(), Some(Background(Rgba([255, 136, 0, 255]))));
+    assert_eq!("#ff880080".parse::<Background>().ok(), Some(Background(Rgba([255, 136, 0, 128]))));
+    assert_eq!("#FFFFFF".parse::<Background>().ok(), Some(Background::WHITE));
+
+    assert!("ff8800".parse::<Background>().is_err());
+    assert!("#ff88".parse::<Background>().is_err());
+    assert!("#gg8800".parse::<Background>().is_err());
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn load_vector_with() -> io::Result<()> {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="1in" height="1in"/>"#;
+
+    let default = Image::load_vector_with(Cursor::new(svg), &usvg::Options::default())?;
+    assert_eq!(default.dimensions(), (96.0, 96.0));
+
+    let opts = usvg::Options { dpi: 192.0, ..usvg::Options::default() };
+    let custom = Image::load_vector_with(Cursor::new(svg), &opts)?;
+    assert_eq!(custom.dimensions(), (192.0, 192.0));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_channels() -> io::Result<()> {
+    let svg = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 8">
+            <rect width="8" height="4" fill="red"/>
+            <rect width="8" height="4" y="4" fill="blue" fill-opacity="0.5"/>
+        </svg>"#
+    )?;
+
+    let raster = svg.rasterize(resample::nearest, (8, 8)).expect("Failed");
+    assert_eq!(raster.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    assert_eq!(raster.get_pixel(0, 7), Rgba([0, 0, 255, 127]));
+
+    Ok(())
+}
+
+#[test]
+fn framed() -> io::Result<()> {
+    let icons: [(&str, &[u8]); 2] = [("icon-16.png", &[1, 2, 3]), ("icon-32.png", &[])];
+
+    let mut buf = Vec::new();
+    encode::framed_write(&icons, &mut buf)?;
+
+    let read = decode::framed_read(&mut Cursor::new(&buf))?;
+    assert_eq!(read, vec![
+        (String::from("icon-16.png"), vec![1, 2, 3]),
+        (String::from("icon-32.png"), vec![])
+    ]);
+
+    buf.pop();
+    let truncated = decode::framed_read(&mut Cursor::new(&buf));
+    assert_eq!(truncated.map_err(|err| err.kind()), Err(io::ErrorKind::UnexpectedEof));
+
+    Ok(())
+}
+
+#[test]
+fn overfit_oversized() -> io::Result<()> {
+    let mut source = RgbaImage::new(40, 40);
+    source.put_pixel(4, 4, Rgba([255, 0, 0, 255]));
+    source.put_pixel(35, 35, Rgba([0, 0, 255, 255]));
+
+    let output = resample::overfit(&DynamicImage::ImageRgba8(source), (32, 32))?;
+    assert_eq!(output.dimensions(), (32, 32));
+
+    // The source should be cropped around it's center
+    assert_eq!(output.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    assert_eq!(output.get_pixel(31, 31), Rgba([0, 0, 255, 255]));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_on_background() -> io::Result<()> {
+    let svg = usvg::Tree::from_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 8">
+            <rect width="8" height="4" fill="blue"/>
+        </svg>"#,
+        &usvg::Options::default()
+    ).expect("Failed to parse svg");
+
+    let red = Rgba([255, 0, 0, 255]);
+    let raster = resample::svg_on_background(&svg, (8, 8), red).expect("Failed");
+
+    assert_eq!(raster.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+    assert_eq!(raster.get_pixel(0, 7), red);
+
+    // The borders added to fit the dimensions are filled as well
+    let raster = resample::svg_on_background(&svg, (16, 8), red).expect("Failed");
+    assert_eq!(raster.get_pixel(0, 0), red);
+    assert_eq!(raster.get_pixel(8, 0), Rgba([0, 0, 255, 255]));
+
+    let translucent = Background(Rgba([255, 0, 0, 128]));
+    let raster = resample::svg_on_background(&svg, (8, 8), translucent).expect("Failed");
+    assert_eq!(raster.get_pixel(0, 0).0[..3], [0, 0, 255]);
+    assert_eq!(raster.get_pixel(0, 7), Rgba([255, 0, 0, 128]));
+
+    Ok(())
+}
+
+#[test]
+fn scale_extreme_aspect_ratio() {
+    let banner = DynamicImage::ImageRgba8(
+        RgbaImage::from_pixel(200, 1, Rgba([255, 0, 0, 255]))
+    );
+
+    let output = resample::apply(resample::linear, &banner, (32, 32)).expect("Failed");
+    assert_eq!(output.dimensions(), (32, 32));
+
+    for (_, y, px) in output.pixels() {
+        assert_eq!(px[3] > 0, y == 15);
+    }
+}
+
+/// Builds a _RIFF_ chunk.
+fn riff_chunk(fourcc: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut chunk = fourcc.to_vec();
+    chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    chunk.extend_from_slice(payload);
+    if payload.len() % 2 == 1 { chunk.push(0); }
+
+    chunk
+}
+
+#[test]
+fn webp_frame_count() -> io::Result<()> {
+    let mut frame = vec![0; 16];
+    frame.extend(riff_chunk(b"VP8 ", &[0; 11]));
+
+    let mut payload = b"WEBP".to_vec();
+    payload.extend(riff_chunk(b"VP8X", &[0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+    payload.extend(riff_chunk(b"ANIM", &[0; 6]));
+    payload.extend(riff_chunk(b"ANMF", &frame));
+    payload.extend(riff_chunk(b"ANMF", &frame));
+    payload.extend(riff_chunk(b"ANMF", &frame));
+
+    let animated = riff_chunk(b"RIFF", &payload);
+    assert_eq!(Image::frame_count(Cursor::new(&animated))?, 3);
+
+    let mut buf = Vec::new();
+    encode::png(&DynamicImage::new_rgba8(16, 16), &mut buf)?;
+    assert_eq!(Image::frame_count(Cursor::new(&buf))?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn webp_animation() -> io::Result<()> {
+    // A translucent 16x16 frame, followed by a 4x4 frame at (8, 8) which
+    // replaces that area of the canvas with darker, more opaque pixels. Both
+    // frames are lossy and have compressed `ALPH` chunks
+    assert_eq!(Image::frame_count(File::open("tests/animated.webp")?)?, 2);
+
+    let image = Image::load(File::open("tests/animated.webp")?)?;
+    let first = image.as_raster().expect("Failed");
+    assert_eq!(first.dimensions(), (16, 16));
+    assert_eq!(first.get_pixel(9, 9)[3], 128);
+
+    Ok(())
+}
+
+#[test]
+fn nearest_small_upscale() {
+    let source = DynamicImage::ImageRgba8(
+        RgbaImage::from_pixel(30, 30, Rgba([255, 0, 0, 255]))
+    );
+
+    let output = resample::apply(resample::nearest, &source, (32, 32)).expect("Failed");
+    assert_eq!(output.dimensions(), (32, 32));
+    assert_eq!(output.get_pixel(16, 16), Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+fn mitchell() {
+    let mut source = RgbaImage::from_pixel(64, 32, Rgba([255, 255, 255, 255]));
+    for (x, _, px) in source.enumerate_pixels_mut() {
+        if x >= 32 { *px = Rgba([0, 0, 0, 255]); }
+    }
+
+    let source = DynamicImage::ImageRgba8(source);
+    let output = resample::apply(resample::mitchell, &source, (32, 32)).expect("Failed");
+
+    assert_eq!(output.dimensions(), (32, 32));
+    assert_eq!(output.get_pixel(0, 8), Rgba([255, 255, 255, 255]));
+    assert_eq!(output.get_pixel(31, 8), Rgba([0, 0, 0, 255]));
+    assert_eq!(output.get_pixel(0, 0)[3], 0);
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn load_vector_limited() -> io::Result<()> {
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 8">{}</svg>"#,
+        "<g><rect width=\"1\" height=\"1\"/></g>".repeat(64)
+    );
+
+    let limits = SvgLimits { max_nodes: 256, ..SvgLimits::default() };
+    assert!(Image::load_vector_limited(Cursor::new(&svg), &usvg::Options::default(), limits).is_ok());
+
+    let limits = SvgLimits { max_nodes: 100, ..SvgLimits::default() };
+    let err = Image::load_vector_limited(Cursor::new(&svg), &usvg::Options::default(), limits);
+    assert_eq!(err.map(|_| ()).map_err(|err| err.kind()), Err(io::ErrorKind::InvalidInput));
+
+    let limits = SvgLimits { max_bytes: 64, ..SvgLimits::default() };
+    let err = Image::load_vector_limited(Cursor::new(&svg), &usvg::Options::default(), limits);
+    assert_eq!(err.map(|_| ()).map_err(|err| err.kind()), Err(io::ErrorKind::InvalidInput));
+
+    Ok(())
+}
+
+#[test]
+fn gaussian() {
+    let checkerboard = DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 256, |x, y| {
+        if (x + y) % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+    }));
+
+    let output = resample::apply(resample::gaussian, &checkerboard, (32, 32)).expect("Failed");
+    assert_eq!(output.dimensions(), (32, 32));
+
+    // A smoothed checkerboard is a flat mid-gray
+    for (x, y, px) in output.pixels() {
+        if x > 0 && y > 0 && x < 31 && y < 31 {
+            assert!(px[0] > 112 && px[0] < 144, "{:?}", px);
+        }
+    }
+}
+
+#[test]
+fn overfit_with() -> io::Result<()> {
+    let source = DynamicImage::ImageRgba8(
+        RgbaImage::from_pixel(16, 32, Rgba([255, 0, 0, 255]))
+    );
+    let white = Rgba([255, 255, 255, 255]);
+
+    let output = resample::overfit_with(&source, (32, 32), white)?;
+    assert_eq!(output.dimensions(), (32, 32));
+    assert_eq!(output.get_pixel(0, 16), white);
+    assert_eq!(output.get_pixel(16, 16), Rgba([255, 0, 0, 255]));
+    assert_eq!(output.get_pixel(31, 16), white);
+
+    let mut filter = resample::on_background(resample::nearest, Background::BLACK);
+    let output = resample::apply(&mut filter, &source, (32, 32)).expect("Failed");
+    assert_eq!(output.get_pixel(0, 16), Rgba([0, 0, 0, 255]));
+    assert_eq!(output.get_pixel(16, 16), Rgba([255, 0, 0, 255]));
+
+    Ok(())
+}
+
+#[test]
+fn ensure_rgba() {
+    let mut image = Image::from(DynamicImage::new_luma8(16, 16));
+    image.ensure_rgba();
+
+    match image.as_raster() {
+        Some(DynamicImage::ImageRgba8(buf)) => assert_eq!(buf.dimensions(), (16, 16)),
+        _ => panic!("The image was not converted to RGBA8")
+    }
+}
+
+#[test]
+fn fit_modes() -> io::Result<()> {
+    // A red 100x50 image with a green stripe along it's left edge
+    let source = DynamicImage::ImageRgba8(RgbaImage::from_fn(100, 50, |x, _| {
+        if x < 20 { Rgba([0, 255, 0, 255]) } else { Rgba([255, 0, 0, 255]) }
+    }));
+
+    let contain = resample::fit(&source, (64, 64), FitMode::Contain, FilterType::Nearest)?;
+    assert_eq!(contain.dimensions(), (64, 64));
+    assert_eq!(contain.get_pixel(32, 0)[3], 0);
+    assert_eq!(contain.get_pixel(0, 32), Rgba([0, 255, 0, 255]));
+    assert_eq!(contain.get_pixel(63, 32), Rgba([255, 0, 0, 255]));
+
+    let cover = resample::fit(&source, (64, 64), FitMode::Cover, FilterType::Nearest)?;
+    assert_eq!(cover.dimensions(), (64, 64));
+    assert!(cover.pixels().all(|(_, _, px)| px == Rgba([255, 0, 0, 255])));
+
+    // An empty image can't be scaled up to cover anything
+    let empty = DynamicImage::new_rgba8(0, 0);
+    match resample::fit(&empty, (64, 64), FitMode::Cover, FilterType::Nearest) {
+        Err(err) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+        Ok(_) => panic!("Covered a target with an empty image")
+    }
+
+    let stretch = resample::fit(&source, (64, 64), FitMode::Stretch, FilterType::Nearest)?;
+    assert_eq!(stretch.dimensions(), (64, 64));
+    assert_eq!(stretch.get_pixel(0, 0), Rgba([0, 255, 0, 255]));
+    assert_eq!(stretch.get_pixel(63, 63), Rgba([255, 0, 0, 255]));
+
+    Ok(())
+}
+
+#[test]
+fn mono_mask() {
+    let source = DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 32, |x, _| {
+        if x < 16 { Rgba([0, 0, 0, 200]) } else { Rgba([0, 0, 0, 50]) }
+    }));
+
+    let mask = resample::mono_mask(&source, (32, 32), 128).expect("Failed");
+    let mask = mask.as_luma8().expect("The mask is not a Luma8 image");
+
+    assert_eq!(mask.dimensions(), (32, 32));
+    assert_eq!(mask.get_pixel(4, 16)[0], 255);
+    assert_eq!(mask.get_pixel(28, 16)[0], 0);
+}
+
+#[test]
+fn linear_srgb() {
+    let checkerboard = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+        if (x + y) % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+    }));
+
+    let brightness = |img: &DynamicImage| {
+        img.pixels().map(|(_, _, px)| px[0] as u64).sum::<u64>() / (32 * 32)
+    };
+
+    let plain = resample::apply(resample::linear, &checkerboard, (32, 32)).expect("Failed");
+    let gamma = resample::apply(resample::linear_srgb, &checkerboard, (32, 32)).expect("Failed");
+
+    // Half of the light of a white pixel is brighter than mid-gray in sRGB
+    assert!(brightness(&plain) < 140);
+    assert!(brightness(&gamma) > 170);
+}
+
+#[test]
+fn resample_filter_from_str() {
+    assert_eq!("nearest".parse::<ResampleFilter>().ok(), Some(ResampleFilter::Nearest));
+    assert_eq!("linear".parse::<ResampleFilter>().ok(), Some(ResampleFilter::Linear));
+    assert_eq!("Cubic".parse::<ResampleFilter>().ok(), Some(ResampleFilter::Cubic));
+    assert_eq!("linear-srgb".parse::<ResampleFilter>().ok(), Some(ResampleFilter::LinearSrgb));
+
+    assert!("bicubic".parse::<ResampleFilter>().is_err());
+    assert!("".parse::<ResampleFilter>().is_err());
+
+    let source = Image::from(DynamicImage::new_rgba8(64, 64));
+    let filter = ResampleFilter::Cubic;
+    let output = source.rasterize(|src, size| filter.apply(src, size), (32, 32)).expect("Failed");
+    assert_eq!(output.dimensions(), (32, 32));
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_dimensions_without_view_box() -> io::Result<()> {
+    let image = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="48"/>"#
+    )?;
+
+    assert_eq!(image.dimensions(), (64.0, 48.0));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_view_box() -> io::Result<()> {
+    let bounds = |img: &DynamicImage| img.pixels()
+        .filter(|(_, _, px)| px[3] > 0)
+        .fold((std::u32::MAX, std::u32::MAX, 0, 0), |(x0, y0, x1, y1), (x, y, _)| {
+            (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+        });
+
+    // Off-origin view boxes
+    let svg = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="10 10 80 80">
+            <rect x="40" y="40" width="20" height="20" fill="red"/>
+        </svg>"#
+    )?;
+
+    let raster = svg.rasterize(resample::nearest, (80, 80)).expect("Failed");
+    assert_eq!(bounds(&raster), (30, 30, 49, 49));
+
+    // View boxes whose aspect ratio differs from the one of the document
+    let svg = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50" viewBox="0 0 80 80">
+            <rect width="80" height="80" fill="red"/>
+        </svg>"#
+    )?;
+
+    let raster = svg.rasterize(resample::nearest, (64, 64)).expect("Failed");
+    assert_eq!(bounds(&raster), (16, 16, 47, 47));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_with_background() -> io::Result<()> {
+    let svg = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 8">
+            <circle cx="4" cy="4" r="2" fill="blue"/>
+        </svg>"#
+    )?;
+
+    let green = Rgba([0, 255, 0, 255]);
+    let raster = svg.rasterize_with_background(resample::nearest, (8, 8), green)
+        .expect("Failed");
+
+    assert_eq!(raster.get_pixel(0, 0), green);
+    assert_eq!(raster.get_pixel(4, 4), Rgba([0, 0, 255, 255]));
+
+    Ok(())
+}
+
+#[test]
+fn raster_with_background() -> io::Result<()> {
+    let source = Image::blank(16, 32, Rgba([255, 0, 0, 255]));
+    let raster = source.rasterize_with_background(resample::nearest, (32, 32), Background::WHITE)
+        .expect("Failed");
+
+    // The borders added to fit the dimensions are filled as well
+    assert_eq!(raster.get_pixel(0, 16), Rgba([255, 255, 255, 255]));
+    assert_eq!(raster.get_pixel(16, 16), Rgba([255, 0, 0, 255]));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn rasterizer() -> io::Result<()> {
+    let svg = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 4">
+            <rect width="4" height="4" fill="red"/>
+        </svg>"#
+    )?;
+
+    // The tree is parsed once and rendered at every size
+    let rasterizer = svg.rasterizer()?;
+    for &size in &[(8, 8), (32, 32)] {
+        let expected = svg.rasterize(resample::nearest, size).expect("Failed");
+        let raster = rasterizer.rasterize(resample::nearest, size).expect("Failed");
+        assert_eq!(raster.to_rgba().into_raw(), expected.to_rgba().into_raw());
+    }
+
+    let raster = rasterizer.rasterize_exact(FilterType::Nearest, (16, 16)).expect("Failed");
+    assert_eq!(raster.get_pixel(4, 8), Rgba([255, 0, 0, 255]));
+    assert_eq!(raster.get_pixel(12, 8)[3], 0);
+
+    Ok(())
+}
+
+#[test]
+fn phash() -> io::Result<()> {
+    let gradient = DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+        let c = (x * 4) as u8;
+        if y % 16 < 4 { Rgba([c, 0, 255 - c, 255]) } else { Rgba([c, c, c, 255]) }
+    }));
+
+    // A lightly-compressed copy of the gradient
+    let mut jpeg = Vec::new();
+    gradient.write_to(&mut jpeg, ImageOutputFormat::JPEG(80)).expect("Failed to encode");
+    let compressed = Image::load(Cursor::new(jpeg))?;
+
+    let different = Image::from(DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+        if (x / 8 + y / 8) % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([0, 0, 0, 255]) }
+    })));
+
+    let original = Image::from(gradient).phash()?;
+    assert!((original ^ compressed.phash()?).count_ones() <= 4);
+    assert!((original ^ different.phash()?).count_ones() >= 16);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_dpi() -> io::Result<()> {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="25.4mm" height="12.7mm">
+        <rect width="100%" height="100%" fill="red"/>
+    </svg>"#;
+
+    let low = usvg::Options { dpi: 96.0, ..usvg::Options::default() };
+    let high = usvg::Options { dpi: 300.0, ..usvg::Options::default() };
+
+    let (low_w, low_h) = Image::load_vector_with(Cursor::new(svg), &low)?.dimensions();
+    let (high_w, high_h) = Image::load_vector_with(Cursor::new(svg), &high)?.dimensions();
+
+    assert_eq!((low_w.round(), low_h.round()), (96.0, 48.0));
+    assert_eq!((high_w.round(), high_h.round()), (300.0, 150.0));
+
+    let image = Image::load_vector_with(Cursor::new(svg), &high)?;
+    let tree = &image.as_svg().expect("The image is not an svg").tree()?;
+    let raster = resample::svg_with_options(tree, (32, 32), &high).expect("Failed");
+    assert_eq!(raster.get_pixel(16, 16), Rgba([255, 0, 0, 255]));
+
+    Ok(())
+}
+
+#[derive(Default)]
+/// A minimal icon family which stores a _PNG_-encoded icon per size.
+struct PngFamily {
+    icons: Vec<((u32, u32), DynamicImage)>
+}
+
+impl Encode for PngFamily {
+    type Icon = (u32, u32);
+
+    fn len(&self) -> usize {
+        self.icons.len()
+    }
+
+    fn icons(&self) -> Vec<&Self::Icon> {
+        self.icons.iter().map(|(icon, _)| icon).collect()
+    }
+
+    fn remove_icon(&mut self, icon: &Self::Icon) -> bool {
+        let len = self.icons.len();
+        self.icons.retain(|(i, _)| i != icon);
+
+        self.icons.len() < len
+    }
+
+    fn add_icon<F: FnMut(&DynamicImage, (u32, u32)) -> io::Result<DynamicImage>>(
+        &mut self,
+        filter: F,
+        source: &Image,
+        icon: Self::Icon,
+    ) -> Result<&mut Self, EncodingError<Self::Icon>> {
+        if self.contains_icon(&icon) {
+            return Err(EncodingError::AlreadyIncluded(icon));
+        }
+
+        self.icons.push((icon, source.rasterize(filter, icon)?));
+        Ok(self)
+    }
+}
+
+impl encode::Write for PngFamily {
+    fn write<W: io::Write>(&mut self, w: &mut W) -> io::Result<&mut Self> {
+        let mut frames = Vec::with_capacity(self.icons.len());
+        for ((width, height), image) in &self.icons {
+            let mut buf = Vec::new();
+            encode::png(image, &mut buf)?;
+            frames.push((format!("{}x{}.png", width, height), buf));
+        }
+
+        let frames: Vec<(&str, &[u8])> = frames.iter()
+            .map(|(name, buf)| (name.as_ref(), buf.as_ref()))
+            .collect();
+
+        encode::framed_write(&frames, w)?;
+        Ok(self)
+    }
+}
+
+#[test]
+fn save_error_path() {
+    let source = Image::from(DynamicImage::new_rgba8(32, 32));
+    let mut family = PngFamily::default();
+    family.add_icon(resample::nearest, &source, (16, 16)).expect("Failed");
+
+    let path = std::env::temp_dir().join("ikon-missing-dir").join("icon.bin");
+    let err = family.save(&path).map(|_| ()).expect_err("Saved into a missing directory");
+
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().contains(&path.display().to_string()));
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn add_icons_parallel() -> io::Result<()> {
+    let source = Image::from(DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 256, |x, y| {
+        Rgba([x as u8, y as u8, (x ^ y) as u8, 255])
+    })));
+    let sizes = [(16, 16), (24, 24), (32, 32), (48, 48), (64, 64), (96, 96), (128, 128), (256, 256)];
+
+    let mut sequential = PngFamily::default();
+    sequential.add_icons(resample::linear, &source, sizes.iter().cloned())?;
+
+    let mut parallel = PngFamily::default();
+    parallel.add_icons_parallel(resample::linear, &source, sizes.iter().cloned())?;
+
+    let (mut seq_buf, mut par_buf) = (Vec::new(), Vec::new());
+    encode::Write::write(&mut sequential, &mut seq_buf)?;
+    encode::Write::write(&mut parallel, &mut par_buf)?;
+    assert_eq!(seq_buf, par_buf);
+
+    // Icons already in the family are rejected before anything is resampled
+    let failing = |_: &DynamicImage, _| Err(io::Error::from(io::ErrorKind::Other));
+    match parallel.add_icons_parallel(failing, &source, vec![(20, 20), (16, 16)]) {
+        Err(EncodingError::AlreadyIncluded((16, 16))) => Ok(()),
+        _ => panic!("Expected EncodingError::AlreadyIncluded")
+    }
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn rasterize_many() -> io::Result<()> {
+    let svg = Image::from_svg_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 32 32">
+            <circle cx="16" cy="16" r="12" fill="red"/>
+        </svg>"#
+    )?;
+    let sizes = [(16, 16), (32, 32), (16, 16), (48, 48)];
+
+    let many = svg.rasterize_many(resample::linear, &sizes).expect("Failed");
+    assert_eq!(many.len(), sizes.len());
+
+    for (image, &size) in many.iter().zip(sizes.iter()) {
+        let single = svg.rasterize(resample::linear, size).expect("Failed");
+        assert_eq!(image.raw_pixels(), single.raw_pixels());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sharpened() {
+    let source = DynamicImage::ImageRgba8(RgbaImage::from_fn(128, 128, |x, _| {
+        if x < 64 { Rgba([64, 64, 64, 255]) } else { Rgba([192, 192, 192, 255]) }
+    }));
+
+    let contrast = |img: &DynamicImage| (1..img.width())
+        .map(|x| (img.get_pixel(x, 16)[0] as i32 - img.get_pixel(x - 1, 16)[0] as i32).abs())
+        .sum::<i32>();
+
+    let soft = resample::apply(resample::linear, &source, (32, 32)).expect("Failed");
+    let sharp = resample::apply(resample::sharpened(resample::linear, 1.0), &source, (32, 32))
+        .expect("Failed");
+
+    assert_eq!(sharp.dimensions(), (32, 32));
+    assert!(contrast(&sharp) > contrast(&soft));
+}
+
+#[test]
+fn std_size() {
+    assert_eq!(StdSize::Small.size(), (16, 16));
+    assert_eq!(StdSize::Medium.size(), (32, 32));
+    assert_eq!(StdSize::Large.size(), (48, 48));
+    assert_eq!(StdSize::XLarge.size(), (256, 256));
+}
+
+#[test]
+fn overfit_odd_centering() -> io::Result<()> {
+    let source = DynamicImage::ImageRgba8(
+        RgbaImage::from_pixel(31, 29, Rgba([255, 0, 0, 255]))
+    );
+
+    // The extra pixel goes to the right and bottom borders
+    let output = resample::overfit(&source, (32, 32))?;
+    assert_eq!(output.get_pixel(0, 1)[3], 255);
+    assert_eq!(output.get_pixel(31, 1)[3], 0);
+    assert_eq!(output.get_pixel(0, 0)[3], 0);
+    assert_eq!(output.get_pixel(0, 29)[3], 255);
+    assert_eq!(output.get_pixel(0, 30)[3], 0);
+
+    Ok(())
+}
+
+#[test]
+fn trimmed() {
+    let mut source = RgbaImage::new(128, 128);
+    for x in 10..42 {
+        for y in 70..102 {
+            source.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        }
+    }
+
+    let source = DynamicImage::ImageRgba8(source);
+    let output = resample::apply(resample::trimmed(resample::nearest), &source, (64, 64))
+        .expect("Failed");
+
+    assert_eq!(output.dimensions(), (64, 64));
+    assert!(output.pixels().all(|(_, _, px)| px == Rgba([255, 0, 0, 255])));
+
+    // Fully transparent images are resampled as is
+    let empty = DynamicImage::new_rgba8(128, 128);
+    let output = resample::apply(resample::trimmed(resample::nearest), &empty, (64, 64))
+        .expect("Failed");
+    assert_eq!(output.dimensions(), (64, 64));
+}
+
+#[test]
+fn png_compression() {
+    // A simple xorshift generator, so that the test image is noisy but
+    // deterministic
+    let mut state = 0x2545_f491_u32;
+    let noisy = RgbaImage::from_fn(64, 64, |x, y| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        Rgba([(state & 0x0f) as u8, (x * 4) as u8, (y * 4) as u8, 255])
+    });
+    let noisy = DynamicImage::ImageRgba8(noisy);
+
+    let mut fast = Vec::new();
+    encode::png_with(&noisy, &mut fast, png::Compression::Fast, png::FilterType::Sub)
+        .expect("Failed");
+
+    let mut best = Vec::new();
+    encode::png_with(&noisy, &mut best, png::Compression::Best, png::FilterType::Sub)
+        .expect("Failed");
+
+    assert!(best.len() <= fast.len());
+
+    let decoded = image::load_from_memory_with_format(&best, ImageFormat::PNG)
+        .expect("Failed");
+    assert_eq!(decoded.to_rgba().into_raw(), noisy.to_rgba().into_raw());
+}
+
+#[test]
+fn load_with_detector() {
+    // A fake format: the signature followed by a single RGBA pixel
+    fn detect(signature: &[u8;8]) -> Option<FormatHandler> {
+        if signature == b"FAKEIMG\0" {
+            Some(load_fake)
+        } else {
+            None
+        }
+    }
+
+    fn load_fake(read: &mut dyn Read) -> io::Result<Image> {
+        let mut buf = [0; 12];
+        read.read_exact(&mut buf)?;
+
+        let px = Rgba([buf[8], buf[9], buf[10], buf[11]]);
+        Ok(Image::from(DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, px))))
+    }
+
+    let fake = b"FAKEIMG\0\x01\x02\x03\x04";
+    let image = Image::load_with_detector(Cursor::new(&fake[..]), detect)
+        .expect("Failed");
+    let raster = image.as_raster().expect("Failed");
+    assert_eq!(raster.get_pixel(0, 0), Rgba([1, 2, 3, 4]));
+
+    // Built-in formats are still detected
+    let mut png = Vec::new();
+    encode::png(&DynamicImage::new_rgba8(2, 2), &mut png).expect("Failed");
+    let image = Image::load_with_detector(Cursor::new(png), detect).expect("Failed");
+    assert!(image.is_raster());
+
+    // Without the detector the fake format is not recognized
+    assert!(Image::load(Cursor::new(&fake[..])).is_err());
+}
+
+#[test]
+fn png_indexed() {
+    let colors = [
+        Rgba([255, 0, 0, 255]),
+        Rgba([0, 255, 0, 255]),
+        Rgba([0, 0, 255, 128]),
+        Rgba([0, 0, 0, 0])
+    ];
+
+    // Scatter the colors so that the RGBA encoding can't compress them away
+    let mut state = 0x2545_f491_u32;
+    let source = RgbaImage::from_fn(16, 16, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        colors[(state % 4) as usize]
+    });
+    let source = DynamicImage::ImageRgba8(source);
+
+    let mut rgba = Vec::new();
+    encode::png(&source, &mut rgba).expect("Failed");
+
+    let mut indexed = Vec::new();
+    encode::png_indexed(&source, &mut indexed).expect("Failed");
+
+    assert!(indexed.len() < rgba.len());
+
+    let decoded = image::load_from_memory_with_format(&indexed, ImageFormat::PNG)
+        .expect("Failed");
+    assert_eq!(decoded.to_rgba().into_raw(), source.to_rgba().into_raw());
+
+    // Images with too many colors fall back to RGBA encoding
+    let noisy = RgbaImage::from_fn(32, 32, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+    let noisy = DynamicImage::ImageRgba8(noisy);
+
+    let mut indexed = Vec::new();
+    encode::png_indexed(&noisy, &mut indexed).expect("Failed");
+
+    let decoded = image::load_from_memory_with_format(&indexed, ImageFormat::PNG)
+        .expect("Failed");
+    assert_eq!(decoded.to_rgba().into_raw(), noisy.to_rgba().into_raw());
+}
+
+#[cfg(feature = "webp-encoder")]
+#[test]
+fn webp() {
+    let source = DynamicImage::ImageRgba8(RgbaImage::from_pixel(16, 16, Rgba([96, 96, 96, 255])));
+
+    let mut lossy = Vec::new();
+    encode::webp(&source, &mut lossy, encode::WebpCompression::Lossy(90.0))
+        .expect("Failed");
+
+    let image = Image::load(Cursor::new(lossy)).expect("Failed");
+    let raster = image.as_raster().expect("Failed");
+    assert_eq!(raster.dimensions(), (16, 16));
+
+    // `image` only decodes the luma plane of lossy streams, so only check
+    // that the solid color survived the round-trip
+    let luma = raster.to_luma();
+    let first = luma.get_pixel(0, 0);
+    assert!(luma.pixels().all(|px| px == first));
+
+    let mut lossless = Vec::new();
+    encode::webp(&source, &mut lossless, encode::WebpCompression::Lossless)
+        .expect("Failed");
+    assert_eq!(&lossless[..4], b"RIFF");
+    assert_eq!(&lossless[8..16], b"WEBPVP8L");
+
+    let translucent = DynamicImage::ImageRgba8(RgbaImage::from_fn(16, 16, |x, y| {
+        Rgba([x as u8 * 16, y as u8 * 16, 128, (x + y) as u8 * 8 + 4])
+    }));
+
+    let mut lossless = Vec::new();
+    encode::webp(&translucent, &mut lossless, encode::WebpCompression::Lossless)
+        .expect("Failed");
+
+    let image = Image::load(Cursor::new(lossless)).expect("Failed");
+    let raster = image.as_raster().expect("Failed");
+    assert_eq!(raster.to_rgba().into_raw(), translucent.to_rgba().into_raw());
+
+    let mut buf = Vec::new();
+    assert!(encode::webp(&source, &mut buf, encode::WebpCompression::Lossy(101.0)).is_err());
+}
+
+#[test]
+fn total_pixels() {
+    let source = Image::from(DynamicImage::new_rgba8(64, 64));
+    let mut family = PngFamily::default();
+    assert_eq!(family.total_pixels(), 0);
+
+    family.add_icons(resample::nearest, &source, vec![(16, 16), (32, 32)]).expect("Failed");
+    assert_eq!(family.total_pixels(), 16 * 16 + 32 * 32);
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_optimized() -> io::Result<()> {
+    let source = Image::from_svg_str(r#"
+        <svg xmlns="http://www.w3.org/2000/svg" width="32" height="32" viewBox="0 0 32 32">
+            <g id="empty"><g id="nested"/></g>
+            <path d="M 1.123456789 1.987654321 L 30.111111111 2.222222222
+                     L 30.333333333 30.444444444 L 1.555555555 30.666666666 Z" fill="red"/>
+        </svg>
+    "#)?;
+    let tree = &source.as_svg().expect("Failed").tree()?;
+
+    let mut lossless = Vec::new();
+    encode::svg(tree, &mut lossless)?;
+
+    let mut optimized = Vec::new();
+    encode::svg_optimized(tree, &mut optimized, 1)?;
+
+    assert!(optimized.len() < lossless.len());
+
+    let text = String::from_utf8(optimized).expect("Failed");
+    assert!(!text.contains("usvg:version"));
+    assert!(!text.contains("<g"));
+    assert!(text.contains("1.1"));
+    assert!(!text.contains("1.12"));
+
+    // The source tree is left untouched
+    let mut again = Vec::new();
+    encode::svg(tree, &mut again)?;
+    assert_eq!(again, lossless);
+
+    // The output still parses back into an equivalent tree
+    let reloaded = Image::from_svg_str(&text)?;
+    let expected = source.rasterize(resample::linear, (32, 32))?;
+    let actual = reloaded.rasterize(resample::linear, (32, 32))?;
+
+    let diff: u32 = expected.to_rgba().pixels()
+        .zip(actual.to_rgba().pixels())
+        .map(|(a, b)| (a[0] as i32 - b[0] as i32).unsigned_abs() + (a[3] as i32 - b[3] as i32).unsigned_abs())
+        .sum();
+    assert!(diff < 32 * 32 * 8);
+
+    Ok(())
+}
+
+#[test]
+fn save_with_callback() -> io::Result<()> {
+    let source = Image::from(DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 256, |x, y| {
+        Rgba([x as u8, y as u8, (x ^ y) as u8, 255])
+    })));
+
+    let mut family = PngFamily::default();
+    family.add_icons(resample::nearest, &source, vec![(64, 64), (128, 128), (256, 256)])
+        .expect("Failed");
+
+    let path = std::env::temp_dir().join("ikon-save-with-callback.bin");
+    let mut reports = Vec::new();
+    family.save_with_callback(&path, |written| reports.push(written))?;
+
+    let len = std::fs::metadata(&path)?.len() as usize;
+    std::fs::remove_file(&path)?;
+
+    // Every write is reported, not only the flushes of the buffer
+    assert!(reports.len() > 1);
+    assert!(reports.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(reports.last(), Some(&len));
+
+    Ok(())
+}
+
+#[test]
+fn remove_icon() {
+    let source = Image::from(DynamicImage::new_rgba8(64, 64));
+    let mut family = PngFamily::default();
+    family.add_icons(resample::nearest, &source, vec![(16, 16), (32, 32)]).expect("Failed");
+
+    assert!(family.remove_icon(&(16, 16)));
+    assert_eq!(family.len(), 1);
+    assert!(!family.remove_icon(&(16, 16)));
+    assert_eq!(family.len(), 1);
+
+    // The icon can be added again once removed
+    family.add_icon(resample::nearest, &source, (16, 16)).expect("Failed");
+    assert_eq!(family.len(), 2);
+}
+
+#[test]
+fn encode_contains_icon() {
+    let source = Image::from(DynamicImage::new_rgba8(64, 64));
+    let mut family = PngFamily::default();
+    assert!(!family.contains_icon(&(16, 16)));
+
+    family.add_icon(resample::nearest, &source, (16, 16)).expect("Failed");
+    assert!(family.contains_icon(&(16, 16)));
+    assert!(!family.contains_icon(&(32, 32)));
+}
+
+#[test]
+fn encode_icons() {
+    let source = Image::from(DynamicImage::new_rgba8(64, 64));
+    let mut family = PngFamily::default();
+    family.add_icons(resample::nearest, &source, vec![(48, 48), (16, 16), (32, 32)])
+        .expect("Failed");
+
+    let mut sizes: Vec<(u32, u32)> = family.icons().iter().map(|icon| icon.size()).collect();
+    sizes.sort();
+
+    assert_eq!(sizes, vec![(16, 16), (32, 32), (48, 48)]);
+}
+
+#[test]
+fn check_source() {
+    let source = Image::from(DynamicImage::new_rgba8(64, 32));
+
+    match encode::check_source::<(u32, u32)>(&source, 1024) {
+        Err(EncodingError::SourceTooLarge(size)) => assert_eq!(size, (64, 32)),
+        _ => panic!("Expected EncodingError::SourceTooLarge")
+    }
+
+    assert!(encode::check_source::<(u32, u32)>(&source, 64 * 32).is_ok());
+    assert!(encode::check_source::<(u32, u32)>(&source, encode::MAX_SOURCE_PIXELS).is_ok());
+
+    let err = io::Error::from(encode::check_source::<(u32, u32)>(&source, 1).unwrap_err());
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn full_into_io_error() {
+    let err = io::Error::from(EncodingError::<(u32, u32)>::Full(256));
+
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(err.to_string().contains("maximum capacity (256 icons)"));
+}
+
+#[test]
+fn mismatched_dimensions_non_square() {
+    let source = DynamicImage::new_rgba8(64, 64);
+    let square = |src: &DynamicImage, (w, _): (u32, u32)| Ok(src.resize_exact(w, w, FilterType::Nearest));
+
+    match resample::apply(square, &source, (16, 32)) {
+        Err(resample::ResampleError::MismatchedDimensions(expected, got)) => {
+            assert_eq!(expected, (16, 32));
+            assert_eq!(got, (16, 16));
+        },
+        _ => panic!("Expected ResampleError::MismatchedDimensions")
+    }
+
+    let output = resample::apply(resample::nearest, &source, (16, 32)).expect("Failed");
+    assert_eq!(output.dimensions(), (16, 32));
+}
+
+#[test]
+fn add_icon_chaining() -> io::Result<()> {
+    let source = Image::from(DynamicImage::new_rgba8(64, 64));
+    let path = std::env::temp_dir().join("ikon-add-icon-chaining.bin");
+
+    PngFamily::default()
+        .add_icon(resample::nearest, &source, (16, 16))?
+        .add_icon(resample::nearest, &source, (32, 32))?
+        .add_icons(resample::nearest, &source, vec![(48, 48), (64, 64)])?
+        .save(&path)?;
+
+    let len = std::fs::metadata(&path)?.len();
+    std::fs::remove_file(&path)?;
+    assert!(len > 0);
+
+    Ok(())
+}
+
+#[test]
+fn cheap_clone() {
+    let source = Image::from(DynamicImage::new_rgba8(256, 256));
+    let clones: Vec<Image> = (0..64).map(|_| source.clone()).collect();
+
+    let original = source.as_raster().expect("Failed");
+    assert!(clones.iter().all(|clone| {
+        std::ptr::eq(clone.as_raster().expect("Failed"), original)
+    }));
+
+    // Modifying a clone does not affect the others
+    let mut gray = Image::from(DynamicImage::new_luma8(16, 16));
+    let copy = gray.clone();
+    gray.ensure_rgba();
+
+    assert!(gray.as_raster().expect("Failed").as_rgba8().is_some());
+    assert!(copy.as_raster().expect("Failed").as_luma8().is_some());
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn image_is_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Image>();
+    assert_send_sync::<Svg>();
+
+    let source = Image::from_svg_str(r#"
+        <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+            <rect width="16" height="16" fill="red"/>
+        </svg>
+    "#).expect("Failed");
+
+    let handles: Vec<_> = (0..4).map(|_| {
+        let source = source.clone();
+        std::thread::spawn(move || source.rasterize(resample::nearest, (16, 16)))
+    }).collect();
+
+    for handle in handles {
+        let raster = handle.join().expect("Failed").expect("Failed");
+        assert_eq!(raster.get_pixel(8, 8), Rgba([255, 0, 0, 255]));
+    }
+}
+
+#[test]
+fn rasterize_exact() -> io::Result<()> {
+    let red = Rgba([255, 0, 0, 255]);
+    let raster = Image::from(DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, red)));
+
+    let exact = raster.rasterize_exact(FilterType::Nearest, (16, 32))?;
+    assert_eq!(exact.dimensions(), (16, 32));
+    assert!(exact.pixels().all(|(_, _, px)| px == red));
+
+    #[cfg(feature = "svg")]
+    {
+        let vector = Image::from_svg_str(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="32" height="32">
+                <rect width="32" height="32" fill="red"/>
+            </svg>
+        "#)?;
+
+        let exact = vector.rasterize_exact(FilterType::Triangle, (16, 32))?;
+        assert_eq!(exact.dimensions(), (16, 32));
+        assert!(exact.pixels().all(|(_, _, px)| px == red));
+
+        // The view box is fitted into the 100x50 canvas, so the square is
+        // 20x20 pixels once the canvas is stretched to 40x20
+        let vector = Image::from_svg_str(r#"
+            <svg xmlns="http://www.w3.org/2000/svg" width="100" height="50" viewBox="0 0 80 80">
+                <rect width="80" height="80" fill="red"/>
+            </svg>
+        "#)?;
+
+        let exact = vector.rasterize_exact(FilterType::Triangle, (40, 20))?;
+        assert_eq!(exact.dimensions(), (40, 20));
+        let opaque = exact.pixels().filter(|(_, _, px)| px[3] > 127).count();
+        assert!((390..=410).contains(&opaque), "{} opaque pixels", opaque);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(feature = "svg"))]
+fn load_without_svg() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 8 8"/>"#;
+
+    match Image::load(Cursor::new(svg)) {
+        Err(err) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+        Ok(_) => panic!("Loaded an svg without the svg feature")
+    }
+}
+
+#[test]
+fn exif_orientation() -> io::Result<()> {
+    // The left half of the image is red and the right half is blue
+    let source = RgbaImage::from_fn(16, 8, |x, _| {
+        if x < 8 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) }
+    });
+
+    let mut jpeg = Vec::new();
+    DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(source).to_rgb())
+        .write_to(&mut jpeg, ImageOutputFormat::JPEG(100))
+        .expect("Failed");
+
+    // Insert an APP1 segment with orientation 6 (rotate 90° clockwise)
+    // right after the SOI marker
+    let tiff: &[u8] = &[
+        b'M', b'M', 0, 42, 0, 0, 0, 8,
+        0, 1,
+        0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0,
+        0, 0, 0, 0
+    ];
+    let mut app1 = vec![0xff, 0xe1];
+    app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    app1.extend_from_slice(b"Exif\0\0");
+    app1.extend_from_slice(tiff);
+
+    let mut rotated = jpeg[..2].to_vec();
+    rotated.extend_from_slice(&app1);
+    rotated.extend_from_slice(&jpeg[2..]);
+
+    let image = Image::load(Cursor::new(rotated))?;
+    let raster = image.as_raster().expect("Failed");
+    assert_eq!(raster.dimensions(), (8, 16));
+
+    // The left of the original image is now at the top
+    let top = raster.get_pixel(4, 2);
+    let bottom = raster.get_pixel(4, 13);
+    assert!(top[0] > 200 && top[2] < 50);
+    assert!(bottom[2] > 200 && bottom[0] < 50);
+
+    // Images without the tag are left as is
+    let image = Image::load(Cursor::new(jpeg))?;
+    assert_eq!(image.as_raster().expect("Failed").dimensions(), (16, 8));
+
+    Ok(())
+}
+
+#[test]
+fn bmp_flatten() {
+    // The left half of the image is fully transparent
+    let source = RgbaImage::from_fn(16, 16, |x, _| {
+        if x < 8 { Rgba([255, 0, 0, 0]) } else { Rgba([0, 0, 255, 255]) }
+    });
+    let source = DynamicImage::ImageRgba8(source);
+
+    let mut buf = Vec::new();
+    encode::bmp(&source, &mut buf).expect("Failed");
+    let decoded = image::load_from_memory_with_format(&buf, ImageFormat::BMP).expect("Failed");
+    assert_eq!(decoded.get_pixel(2, 2), Rgba([255, 255, 255, 255]));
+    assert_eq!(decoded.get_pixel(12, 2), Rgba([0, 0, 255, 255]));
+
+    let mut buf = Vec::new();
+    encode::bmp_with_background(&source, &mut buf, Rgba([0, 255, 0, 255])).expect("Failed");
+    let decoded = image::load_from_memory_with_format(&buf, ImageFormat::BMP).expect("Failed");
+    assert_eq!(decoded.get_pixel(2, 2), Rgba([0, 255, 0, 255]));
+
+    let mut buf = Vec::new();
+    encode::bmp_with_background(&source, &mut buf, Background::BLACK).expect("Failed");
+    let decoded = image::load_from_memory_with_format(&buf, ImageFormat::BMP).expect("Failed");
+    assert_eq!(decoded.get_pixel(2, 2), Rgba([0, 0, 0, 255]));
+
+    let half = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 128])));
+    let flat = encode::flatten(&half, Background::WHITE);
+    assert_eq!(flat.get_pixel(0, 0), Rgba([127, 127, 127, 255]));
+}
+
+#[test]
+fn jpeg() -> io::Result<()> {
+    let source = DynamicImage::ImageRgba8(RgbaImage::from_fn(48, 32, |x, y| {
+        Rgba([x as u8 * 5, y as u8 * 7, 128, 255])
+    }));
+
+    let mut buf = Vec::new();
+    encode::jpeg(&source, &mut buf, 80)?;
+
+    let image = Image::load(Cursor::new(buf))?;
+    assert_eq!(image.as_raster().expect("Failed").dimensions(), (48, 32));
+
+    let mut buf = Vec::new();
+    assert_eq!(encode::jpeg(&source, &mut buf, 0).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(encode::jpeg(&source, &mut buf, 101).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
+
+#[test]
+fn load_frames() -> io::Result<()> {
+    let colors = [Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 255]), Rgba([0, 0, 255, 255])];
+
+    let mut gif = Vec::new();
+    {
+        let mut encoder = image::gif::Encoder::new(&mut gif);
+        let frames = colors.iter().map(|&color| image::Frame::new(RgbaImage::from_pixel(8, 8, color)));
+        encoder.encode_frames(frames).expect("Failed");
+    }
+
+    assert_eq!(Image::frame_count(Cursor::new(&gif))?, 3);
+
+    let frames = Image::load_frames(Cursor::new(&gif))?;
+    assert_eq!(frames.len(), 3);
+    for (frame, &color) in frames.iter().zip(colors.iter()) {
+        assert_eq!(frame.dimensions(), (8, 8));
+        assert_eq!(frame.get_pixel(4, 4), color);
+    }
+
+    // The second frame of the animation only covers a 4x4 area at (8, 8),
+    // without blending
+    let frames = Image::load_frames(File::open("tests/animated.webp")?)?;
+    assert_eq!(frames.len(), 2);
+    assert!(frames.iter().all(|frame| frame.dimensions() == (16, 16)));
+
+    let second = &frames[1];
+    assert_eq!(second.get_pixel(0, 0)[3], 128);
+    assert_eq!(second.get_pixel(9, 9)[3], 192);
+    assert_eq!(second.get_pixel(12, 12)[3], 128);
+    assert!(second.get_pixel(9, 9)[0] < second.get_pixel(0, 0)[0]);
+
+    // Static images have a single frame
+    let mut png = Vec::new();
+    encode::png(&DynamicImage::new_rgba8(4, 4), &mut png)?;
+    assert_eq!(Image::load_frames(Cursor::new(png))?.len(), 1);
+
+    // Vector graphics have no frames to decode
+    let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"/>"#;
+    match Image::load_frames(Cursor::new(&svg[..])) {
+        Err(err) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+        Ok(_) => panic!("Decoded frames from vector graphics")
+    }
+
+    Ok(())
+}
+
+#[test]
+fn blank() -> io::Result<()> {
+    let red = Rgba([255, 0, 0, 255]);
+    let blank = Image::blank(64, 64, red);
+    assert_eq!(blank.dimensions(), (64.0, 64.0));
+
+    let raster = blank.rasterize(resample::nearest, (32, 32))?;
+    assert_eq!(raster.dimensions(), (32, 32));
+    assert!(raster.pixels().all(|(_, _, px)| px == red));
+
+    let transparent = Image::transparent(16, 8);
+    let raster = transparent.as_raster().expect("Failed");
+    assert_eq!(raster.dimensions(), (16, 8));
+    assert!(raster.pixels().all(|(_, _, px)| px[3] == 0));
+
+    Ok(())
+}
+
+#[test]
+fn rounded_and_circle() -> io::Result<()> {
+    let source = Image::blank(64, 64, Rgba([255, 0, 0, 255]));
+
+    let rounded = source.rasterize(resample::rounded(resample::nearest, 8.0), (32, 32))?;
+    for &(x, y) in &[(0, 0), (31, 0), (0, 31), (31, 31)] {
+        assert_eq!(rounded.get_pixel(x, y)[3], 0);
+    }
+    assert_eq!(rounded.get_pixel(16, 16)[3], 255);
+    assert_eq!(rounded.get_pixel(16, 0)[3], 255);
+    assert_eq!(rounded.get_pixel(8, 8)[3], 255);
+
+    let circle = source.rasterize(resample::circle(resample::nearest), (32, 32))?;
+    for &(x, y) in &[(0, 0), (31, 0), (0, 31), (31, 31), (3, 3)] {
+        assert_eq!(circle.get_pixel(x, y)[3], 0);
+    }
+    assert_eq!(circle.get_pixel(16, 16)[3], 255);
+    assert_eq!(circle.get_pixel(16, 1)[3], 255);
+
+    // Degenerate radii leave the image fully opaque
+    for &radius in &[0.0, 0.3, -4.0] {
+        let masked = source.rasterize(resample::rounded(resample::nearest, radius), (32, 32))?;
+        assert!(masked.pixels().all(|(_, _, px)| px[3] == 255));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn padded() -> io::Result<()> {
+    let source = Image::blank(64, 64, Rgba([255, 0, 0, 255]));
+
+    let padded = source.rasterize(resample::padded(resample::linear, 0.1), (100, 100))?;
+    assert_eq!(padded.dimensions(), (100, 100));
+    assert_eq!(resample::content_bounds(&padded, 0), Some((10, 10, 80, 80)));
+
+    let unpadded = source.rasterize(resample::padded(resample::linear, 0.0), (100, 100))?;
+    assert_eq!(resample::content_bounds(&unpadded, 0), Some((0, 0, 100, 100)));
+
+    for &fraction in &[0.5, -0.1] {
+        let mut filter = resample::padded(resample::linear, fraction);
+        match filter(source.as_raster().expect("Failed"), (100, 100)) {
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("Accepted a padding fraction of {}", fraction)
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn content_bounds() -> io::Result<()> {
+    let mut raster = RgbaImage::new(64, 48);
+    for x in 40..50 {
+        for y in 5..25 {
+            raster.put_pixel(x, y, Rgba([0, 0, 255, if x == 40 { 16 } else { 255 }]));
+        }
+    }
+    let image = Image::from(DynamicImage::ImageRgba8(raster));
+
+    assert_eq!(image.content_bounds(0), Some((40, 5, 10, 20)));
+    assert_eq!(image.content_bounds(16), Some((41, 5, 9, 20)));
+    assert_eq!(Image::transparent(16, 16).content_bounds(0), None);
+
+    #[cfg(feature = "svg")]
+    {
+        let vector = Image::from_svg_str(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="10 10 80 60"/>"#
+        )?;
+        assert_eq!(vector.content_bounds(0), Some((0, 0, 80, 60)));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_relative_image() -> io::Result<()> {
+    let dir = std::env::temp_dir().join("ikon-svg-relative-image");
+    std::fs::create_dir_all(&dir)?;
+
+    let green = Rgba([0, 255, 0, 255]);
+    let mut png = File::create(dir.join("green.png"))?;
+    encode::png(&DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, green)), &mut png)?;
+
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"
+        width="8" height="8">
+        <image xlink:href="green.png" width="8" height="8"/>
+    </svg>"#;
+
+    // The reference is resolved against opts.path both when the image is
+    // loaded and whenever the stored document is parsed again
+    let opts = usvg::Options { path: Some(dir.join("icon.svg")), ..usvg::Options::default() };
+    let image = Image::load_vector_with(Cursor::new(svg), &opts)?;
+
+    let raster = image.rasterize(resample::nearest, (8, 8))?;
+    assert_eq!(raster.get_pixel(4, 4), green);
+
+    let svg = image.as_svg().expect("Failed");
+    let raster = resample::svg_with_options(&svg.tree()?, (8, 8), svg.options())?;
+    assert_eq!(raster.get_pixel(4, 4), green);
+
+    Ok(())
+}
+
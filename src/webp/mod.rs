@@ -0,0 +1,267 @@
+//! Decoding of the _RIFF_ container used by _WebP_ files, and of the parts
+//! of the format the `image` crate does not support: lossless bitstreams,
+//! `ALPH` chunks and animations.
+
+use image::{imageops, webp::vp8::VP8Decoder, ImageBuffer, Rgba, RgbaImage};
+use std::{convert::TryInto, io::{self, Cursor}};
+
+mod vp8l;
+
+/// The length of the header of an `ANMF` chunk.
+const ANMF_HEADER_LEN: usize = 16;
+/// The animation flag of the `VP8X` chunk.
+const ANIMATION_FLAG: u8 = 0x02;
+/// The blending method flag of the `ANMF` chunk: frames with this flag set
+/// replace the canvas instead of being alpha-blended over it.
+const NO_BLEND_FLAG: u8 = 0x02;
+/// The disposal method flag of the `ANMF` chunk: the area of frames with
+/// this flag set is cleared before the next frame is drawn.
+const DISPOSE_FLAG: u8 = 0x01;
+
+/// Returns the payloads of the `ANMF` chunks of an animated _WebP_ file.
+/// 
+/// Returns an empty `Vec` if the file is not animated.
+pub(crate) fn frames(data: &[u8]) -> io::Result<Vec<&[u8]>> {
+    let chunks = chunks(riff_payload(data)?)?;
+
+    match canvas(&chunks) {
+        Some((flags, _)) if flags & ANIMATION_FLAG != 0 => {},
+        _ => return Ok(Vec::new())
+    }
+
+    Ok(chunks.into_iter()
+        .filter(|(fourcc, _)| fourcc == b"ANMF")
+        .map(|(_, payload)| payload)
+        .collect())
+}
+
+/// Decodes a still _WebP_ file.
+/// 
+/// Returns `Ok(None)` for simple lossy files, which the `image` crate can
+/// decode by itself.
+pub(crate) fn still(data: &[u8]) -> io::Result<Option<RgbaImage>> {
+    let chunks = chunks(riff_payload(data)?)?;
+
+    match chunks.first() {
+        Some((fourcc, _)) if fourcc == b"VP8 " => Ok(None),
+        _ => frame(&chunks).map(Some)
+    }
+}
+
+/// An iterator over the frames of an animated _WebP_ file, composited onto
+/// the canvas of the animation.
+pub(crate) struct Animation<'a> {
+    canvas: RgbaImage,
+    frames: std::vec::IntoIter<&'a [u8]>,
+    /// The area of the previous frame, if it is to be cleared before the
+    /// next frame is drawn.
+    dispose: Option<(u32, u32, u32, u32)>
+}
+
+impl<'a> Animation<'a> {
+    /// Returns the frames of `data`, or `Ok(None)` if the file is not
+    /// animated.
+    pub(crate) fn new(data: &'a [u8]) -> io::Result<Option<Self>> {
+        let frames = frames(data)?;
+        if frames.is_empty() {
+            return Ok(None);
+        }
+
+        let (_, (width, height)) = canvas(&chunks(riff_payload(data)?)?)
+            .ok_or_else(invalid_data)?;
+
+        Ok(Some(Animation {
+            canvas: ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0])),
+            frames: frames.into_iter(),
+            dispose: None
+        }))
+    }
+
+    /// Draws the frame stored in the payload of an `ANMF` chunk onto the
+    /// canvas.
+    fn draw(&mut self, anmf: &[u8]) -> io::Result<RgbaImage> {
+        if anmf.len() < ANMF_HEADER_LEN {
+            return Err(invalid_data());
+        }
+
+        if let Some((x, y, width, height)) = self.dispose.take() {
+            let clear = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+            imageops::replace(&mut self.canvas, &clear, x, y);
+        }
+
+        let x = 2 * u24(&anmf[0..3]);
+        let y = 2 * u24(&anmf[3..6]);
+        let width = u24(&anmf[6..9]) + 1;
+        let height = u24(&anmf[9..12]) + 1;
+        let flags = anmf[15];
+
+        let frame = frame(&chunks(&anmf[ANMF_HEADER_LEN..])?)?;
+
+        if frame.dimensions() != (width, height)
+            || x + width > self.canvas.width()
+            || y + height > self.canvas.height()
+        {
+            return Err(invalid_data());
+        }
+
+        if flags & NO_BLEND_FLAG != 0 {
+            imageops::replace(&mut self.canvas, &frame, x, y);
+        } else {
+            imageops::overlay(&mut self.canvas, &frame, x, y);
+        }
+
+        if flags & DISPOSE_FLAG != 0 {
+            self.dispose = Some((x, y, width, height));
+        }
+
+        Ok(self.canvas.clone())
+    }
+}
+
+impl<'a> Iterator for Animation<'a> {
+    type Item = io::Result<RgbaImage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let anmf = self.frames.next()?;
+        Some(self.draw(anmf))
+    }
+}
+
+/// Decodes the bitstream of a frame, along with its `ALPH` chunk.
+/// 
+/// The `image` crate only decodes the luma plane of lossy bitstreams, so
+/// lossy frames are decoded in grayscale.
+fn frame(chunks: &[(&[u8], &[u8])]) -> io::Result<RgbaImage> {
+    let find = |name: &[u8; 4]| {
+        chunks.iter().find(|(fourcc, _)| fourcc == name).map(|(_, payload)| *payload)
+    };
+
+    if let Some(bitstream) = find(b"VP8L") {
+        let (width, height, pixels) = vp8l::decode(bitstream)?;
+        let mut rgba = Vec::with_capacity(4 * pixels.len());
+
+        for px in pixels {
+            let [a, r, g, b] = px.to_be_bytes();
+            rgba.extend_from_slice(&[r, g, b, a]);
+        }
+
+        return ImageBuffer::from_raw(width, height, rgba).ok_or_else(invalid_data);
+    }
+
+    let bitstream = find(b"VP8 ").ok_or_else(invalid_data)?;
+    let mut decoder = VP8Decoder::new(Cursor::new(bitstream));
+    let luma = decoder.decode_frame().map_err(crate::image_err_to_io)?;
+    let (width, height) = (luma.width as u32, luma.height as u32);
+
+    let alpha = match find(b"ALPH") {
+        Some(chunk) => alpha(chunk, width, height)?,
+        None => vec![0xff; luma.ybuf.len()]
+    };
+
+    let mut rgba = Vec::with_capacity(4 * alpha.len());
+    for (&l, a) in luma.ybuf.iter().zip(alpha) {
+        rgba.extend_from_slice(&[l, l, l, a]);
+    }
+
+    ImageBuffer::from_raw(width, height, rgba).ok_or_else(invalid_data)
+}
+
+/// Decodes the alpha plane stored in an `ALPH` chunk.
+fn alpha(chunk: &[u8], width: u32, height: u32) -> io::Result<Vec<u8>> {
+    let (&header, data) = chunk.split_first().ok_or_else(invalid_data)?;
+    let len = width as usize * height as usize;
+
+    let mut alpha = match header & 0x03 {
+        0 => data.get(..len).ok_or_else(invalid_data)?.to_vec(),
+        1 => vp8l::decode_headerless(data, width, height)?
+            .into_iter()
+            .map(|px| (px >> 8) as u8)
+            .collect(),
+        _ => return Err(invalid_data())
+    };
+
+    // Revert the prediction filter, which predicts each value from its
+    // neighbours to the left, to the top or both
+    let filter = (header >> 2) & 0x03;
+    let width = width as usize;
+
+    for y in 0..height as usize {
+        let row = y * width;
+
+        for x in 0..width {
+            let i = row + x;
+            let prediction = match (x, y, filter) {
+                (_, _, 0) => 0,
+                (0, 0, _) => 0,
+                (_, 0, _) => alpha[i - 1],
+                (0, _, _) => alpha[i - width],
+                (_, _, 1) => alpha[i - 1],
+                (_, _, 2) => alpha[i - width],
+                _ => {
+                    let gradient = alpha[i - 1] as i32 + alpha[i - width] as i32
+                        - alpha[i - width - 1] as i32;
+                    gradient.max(0).min(255) as u8
+                }
+            };
+
+            alpha[i] = alpha[i].wrapping_add(prediction);
+        }
+    }
+
+    Ok(alpha)
+}
+
+/// Returns the flags and the canvas size stored in the `VP8X` chunk of a
+/// file, if any.
+fn canvas(chunks: &[(&[u8], &[u8])]) -> Option<(u8, (u32, u32))> {
+    chunks.iter()
+        .find(|(fourcc, payload)| fourcc == b"VP8X" && payload.len() >= 10)
+        .map(|(_, payload)| {
+            (payload[0], (u24(&payload[4..7]) + 1, u24(&payload[7..10]) + 1))
+        })
+}
+
+/// Returns the contents of the `RIFF` chunk of a _WebP_ file, excluding the
+/// `WEBP` form type.
+fn riff_payload(data: &[u8]) -> io::Result<&[u8]> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(invalid_data());
+    }
+
+    let len = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    Ok(&data[12..data.len().min(8 + len)])
+}
+
+/// Splits a sequence of _RIFF_ chunks into their four-character codes and
+/// payloads.
+fn chunks(mut data: &[u8]) -> io::Result<Vec<(&[u8], &[u8])>> {
+    let mut chunks = Vec::new();
+
+    while data.len() >= 8 {
+        let (fourcc, rest) = data.split_at(4);
+        let (len, rest) = rest.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+
+        if rest.len() < len {
+            return Err(invalid_data());
+        }
+
+        chunks.push((fourcc, &rest[..len]));
+
+        // Chunks are padded to an even length
+        data = &rest[(len + len % 2).min(rest.len())..];
+    }
+
+    Ok(chunks)
+}
+
+/// Reads a little-endian 24-bit unsigned integer.
+#[inline]
+fn u24(bytes: &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16
+}
+
+#[inline]
+fn invalid_data() -> io::Error {
+    io::Error::from(io::ErrorKind::InvalidData)
+}
@@ -0,0 +1,573 @@
+//! Decoding of the lossless (`VP8L`) bitstream of _WebP_ files.
+
+use super::invalid_data;
+use std::io;
+
+/// The signature of a `VP8L` bitstream.
+const SIGNATURE: u32 = 0x2f;
+/// The number of literal symbols of the green alphabet, plus the number of
+/// _LZ77_ length prefixes.
+const GREEN_ALPHABET: usize = 256 + 24;
+/// The size of the distance alphabet.
+const DISTANCE_ALPHABET: usize = 40;
+/// The order in which the code lengths of the code length code are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
+];
+/// The offsets of the 120 shortest distance codes, as `(y << 4) | (8 - x)`.
+const DISTANCE_MAP: [u8; 120] = [
+    0x18, 0x07, 0x17, 0x19, 0x28, 0x06, 0x27, 0x29, 0x16, 0x1a,
+    0x26, 0x2a, 0x38, 0x05, 0x37, 0x39, 0x15, 0x1b, 0x36, 0x3a,
+    0x25, 0x2b, 0x48, 0x04, 0x47, 0x49, 0x14, 0x1c, 0x35, 0x3b,
+    0x46, 0x4a, 0x24, 0x2c, 0x58, 0x45, 0x4b, 0x34, 0x3c, 0x03,
+    0x57, 0x59, 0x13, 0x1d, 0x56, 0x5a, 0x23, 0x2d, 0x44, 0x4c,
+    0x55, 0x5b, 0x33, 0x3d, 0x68, 0x02, 0x67, 0x69, 0x12, 0x1e,
+    0x66, 0x6a, 0x22, 0x2e, 0x54, 0x5c, 0x43, 0x4d, 0x65, 0x6b,
+    0x32, 0x3e, 0x78, 0x01, 0x77, 0x79, 0x53, 0x5d, 0x11, 0x1f,
+    0x64, 0x6c, 0x42, 0x4e, 0x76, 0x7a, 0x21, 0x2f, 0x75, 0x7b,
+    0x31, 0x3f, 0x63, 0x6d, 0x52, 0x5e, 0x00, 0x74, 0x7c, 0x41,
+    0x4f, 0x10, 0x20, 0x62, 0x6e, 0x30, 0x73, 0x7d, 0x51, 0x5f,
+    0x40, 0x72, 0x7e, 0x61, 0x6f, 0x50, 0x71, 0x7f, 0x60, 0x70
+];
+
+/// Decodes a `VP8L` bitstream to its dimensions and its pixels, packed as
+/// `0xAARRGGBB`.
+pub(crate) fn decode(data: &[u8]) -> io::Result<(u32, u32, Vec<u32>)> {
+    let mut br = BitReader::new(data);
+
+    if br.read(8)? != SIGNATURE {
+        return Err(invalid_data());
+    }
+
+    let width = br.read(14)? + 1;
+    let height = br.read(14)? + 1;
+    let _alpha_is_used = br.read(1)?;
+
+    if br.read(3)? != 0 {
+        return Err(invalid_data());
+    }
+
+    let pixels = decode_image(&mut br, width as usize, height as usize)?;
+    Ok((width, height, pixels))
+}
+
+/// Decodes a `VP8L` image stream without the signature and dimensions
+/// header, as stored by compressed `ALPH` chunks.
+pub(crate) fn decode_headerless(data: &[u8], width: u32, height: u32) -> io::Result<Vec<u32>> {
+    decode_image(&mut BitReader::new(data), width as usize, height as usize)
+}
+
+/// A transform applied to the pixels of an image by the encoder.
+enum Transform {
+    Predictor { bits: u32, modes: Vec<u32> },
+    Color { bits: u32, multipliers: Vec<u32> },
+    SubtractGreen,
+    ColorIndexing { bits: u32, palette: Vec<u32> }
+}
+
+/// Decodes the main image of a bitstream, reverting its transforms.
+fn decode_image(br: &mut BitReader, width: usize, height: usize) -> io::Result<Vec<u32>> {
+    let mut transforms = Vec::new();
+    let mut seen = 0u8;
+    let mut xsize = width;
+
+    while br.read(1)? == 1 {
+        let kind = br.read(2)?;
+
+        // Each transform may only be used once
+        if seen & (1 << kind) != 0 {
+            return Err(invalid_data());
+        }
+        seen |= 1 << kind;
+
+        let transform = match kind {
+            0 | 1 => {
+                let bits = br.read(3)? + 2;
+                let data = decode_entropy_image(
+                    br,
+                    subsample(xsize, bits),
+                    subsample(height, bits),
+                    false
+                )?;
+
+                if kind == 0 {
+                    Transform::Predictor { bits, modes: data }
+                } else {
+                    Transform::Color { bits, multipliers: data }
+                }
+            },
+            2 => Transform::SubtractGreen,
+            _ => {
+                let len = br.read(8)? as usize + 1;
+                let mut palette = decode_entropy_image(br, len, 1, false)?;
+
+                // The palette is delta-coded
+                for i in 1..len {
+                    palette[i] = add_pixels(palette[i], palette[i - 1]);
+                }
+
+                let bits = match len {
+                    0..=2 => 3,
+                    3..=4 => 2,
+                    5..=16 => 1,
+                    _ => 0
+                };
+
+                Transform::ColorIndexing { bits, palette }
+            }
+        };
+
+        transforms.push((xsize, transform));
+
+        // Color-indexed pixels are bundled together
+        if let Some((_, Transform::ColorIndexing { bits, .. })) = transforms.last() {
+            xsize = subsample(xsize, *bits);
+        }
+    }
+
+    let mut pixels = decode_entropy_image(br, xsize, height, true)?;
+
+    for (xsize, transform) in transforms.iter().rev() {
+        pixels = match transform {
+            Transform::Predictor { bits, modes } => {
+                unpredict(&mut pixels, *xsize, *bits, modes);
+                pixels
+            },
+            Transform::Color { bits, multipliers } => {
+                uncorrelate(&mut pixels, *xsize, *bits, multipliers);
+                pixels
+            },
+            Transform::SubtractGreen => {
+                for px in pixels.iter_mut() {
+                    let green = (*px >> 8) & 0xff;
+                    *px = add_pixels(*px, (green << 16) | green);
+                }
+
+                pixels
+            },
+            Transform::ColorIndexing { bits, palette } => {
+                unbundle(&pixels, *xsize, height, *bits, palette)
+            }
+        };
+    }
+
+    Ok(pixels)
+}
+
+/// Decodes the entropy-coded pixels of an image, either the main image or
+/// the sub-images of the transforms and of the meta prefix codes.
+fn decode_entropy_image(
+    br: &mut BitReader,
+    xsize: usize,
+    ysize: usize,
+    is_main: bool
+) -> io::Result<Vec<u32>> {
+    let cache_bits = if br.read(1)? == 1 {
+        match br.read(4)? {
+            bits @ 1..=11 => bits,
+            _ => return Err(invalid_data())
+        }
+    } else {
+        0
+    };
+
+    let meta = if is_main && br.read(1)? == 1 {
+        let bits = br.read(3)? + 2;
+        let groups = decode_entropy_image(br, subsample(xsize, bits), subsample(ysize, bits), false)?;
+        Some((bits, subsample(xsize, bits), groups))
+    } else {
+        None
+    };
+
+    let group_count = match &meta {
+        Some((_, _, groups)) => groups.iter().map(|px| (px >> 8) & 0xffff).max().unwrap_or(0) + 1,
+        None => 1
+    };
+
+    let cache_len = if cache_bits > 0 { 1 << cache_bits } else { 0 };
+    let mut groups = Vec::with_capacity(group_count as usize);
+
+    for _ in 0..group_count {
+        groups.push([
+            PrefixCode::parse(br, GREEN_ALPHABET + cache_len)?,
+            PrefixCode::parse(br, 256)?,
+            PrefixCode::parse(br, 256)?,
+            PrefixCode::parse(br, 256)?,
+            PrefixCode::parse(br, DISTANCE_ALPHABET)?
+        ]);
+    }
+
+    let total = xsize.checked_mul(ysize).ok_or_else(invalid_data)?;
+    let mut pixels = Vec::with_capacity(total);
+    let mut cache = vec![0u32; cache_len];
+
+    while pixels.len() < total {
+        let pos = pixels.len();
+        let group = match &meta {
+            Some((bits, width, groups_image)) => {
+                let (x, y) = (pos % xsize, pos / xsize);
+                let index = groups_image[(y >> bits) * width + (x >> bits)];
+                &groups[((index >> 8) & 0xffff) as usize]
+            },
+            None => &groups[0]
+        };
+
+        let green = group[0].read_symbol(br)? as usize;
+
+        if green < 256 {
+            let red = group[1].read_symbol(br)? as u32;
+            let blue = group[2].read_symbol(br)? as u32;
+            let alpha = group[3].read_symbol(br)? as u32;
+            pixels.push((alpha << 24) | (red << 16) | ((green as u32) << 8) | blue);
+        } else if green < GREEN_ALPHABET {
+            let len = prefix_value(br, green - 256)?;
+            let prefix = group[4].read_symbol(br)? as usize;
+            let code = prefix_value(br, prefix)?;
+            let dist = distance(xsize, code);
+
+            if dist > pos || pos + len > total {
+                return Err(invalid_data());
+            }
+
+            for i in pos..pos + len {
+                let px = pixels[i - dist];
+                pixels.push(px);
+            }
+        } else {
+            let px = *cache.get(green - GREEN_ALPHABET).ok_or_else(invalid_data)?;
+            pixels.push(px);
+        }
+
+        if cache_bits > 0 {
+            for &px in &pixels[pos..] {
+                cache[(0x1e35_a7bd_u32.wrapping_mul(px) >> (32 - cache_bits)) as usize] = px;
+            }
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Reverts the predictor transform.
+fn unpredict(pixels: &mut [u32], xsize: usize, bits: u32, modes: &[u32]) {
+    let width = subsample(xsize, bits);
+
+    for i in 0..pixels.len() {
+        let (x, y) = (i % xsize, i / xsize);
+
+        let prediction = match (x, y) {
+            (0, 0) => 0xff00_0000,
+            (_, 0) => pixels[i - 1],
+            (0, _) => pixels[i - xsize],
+            _ => {
+                let mode = (modes[(y >> bits) * width + (x >> bits)] >> 8) & 0xf;
+                let left = pixels[i - 1];
+                let top = pixels[i - xsize];
+                let top_left = pixels[i - xsize - 1];
+                // The pixels on the rightmost column use the leftmost pixel
+                // of the current row instead
+                let top_right = pixels[i - xsize + 1];
+
+                match mode {
+                    1 => left,
+                    2 => top,
+                    3 => top_right,
+                    4 => top_left,
+                    5 => average(average(left, top_right), top),
+                    6 => average(left, top_left),
+                    7 => average(left, top),
+                    8 => average(top_left, top),
+                    9 => average(top, top_right),
+                    10 => average(average(left, top_left), average(top, top_right)),
+                    11 => select(left, top, top_left),
+                    12 => map_channels(left, top, top_left, |l, t, tl| l + t - tl),
+                    13 => {
+                        let avg = average(left, top);
+                        map_channels(avg, top_left, 0, |a, tl, _| a + (a - tl) / 2)
+                    },
+                    _ => 0xff00_0000
+                }
+            }
+        };
+
+        pixels[i] = add_pixels(pixels[i], prediction);
+    }
+}
+
+/// Reverts the color transform.
+fn uncorrelate(pixels: &mut [u32], xsize: usize, bits: u32, multipliers: &[u32]) {
+    let width = subsample(xsize, bits);
+
+    for (i, px) in pixels.iter_mut().enumerate() {
+        let (x, y) = (i % xsize, i / xsize);
+        let m = multipliers[(y >> bits) * width + (x >> bits)];
+        let (green_to_red, green_to_blue, red_to_blue) = (m as i8, (m >> 8) as i8, (m >> 16) as i8);
+
+        let green = (*px >> 8) as i8;
+        let red = ((*px >> 16) as u8).wrapping_add(color_delta(green_to_red, green));
+        let blue = (*px as u8)
+            .wrapping_add(color_delta(green_to_blue, green))
+            .wrapping_add(color_delta(red_to_blue, red as i8));
+
+        *px = (*px & 0xff00_ff00) | ((red as u32) << 16) | blue as u32;
+    }
+}
+
+/// Reverts the color indexing transform, looking up the pixels of an image
+/// of `xsize` by `ysize` pixels in `palette`.
+fn unbundle(pixels: &[u32], xsize: usize, ysize: usize, bits: u32, palette: &[u32]) -> Vec<u32> {
+    let width = subsample(xsize, bits);
+    let per_pixel = 8 >> bits;
+    let mask = (1 << per_pixel) - 1;
+
+    let mut unbundled = Vec::with_capacity(xsize * ysize);
+
+    for y in 0..ysize {
+        for x in 0..xsize {
+            let bundle = (pixels[y * width + (x >> bits)] >> 8) & 0xff;
+            let shift = (x & ((1 << bits) - 1)) as u32 * per_pixel;
+            let index = (bundle >> shift) & mask;
+
+            // Out-of-range indices are transparent black
+            unbundled.push(palette.get(index as usize).copied().unwrap_or(0));
+        }
+    }
+
+    unbundled
+}
+
+/// A canonical prefix code.
+struct PrefixCode {
+    /// The number of codes of each length.
+    counts: [u16; 16],
+    /// The symbols, sorted by code length and value.
+    symbols: Vec<u16>
+}
+
+impl PrefixCode {
+    /// Reads the code lengths of a prefix code over `alphabet` symbols.
+    fn parse(br: &mut BitReader, alphabet: usize) -> io::Result<Self> {
+        let mut lengths = vec![0u8; alphabet];
+
+        if br.read(1)? == 1 {
+            // A simple code, with one or two symbols
+            let count = br.read(1)? + 1;
+            let first_bits = if br.read(1)? == 1 { 8 } else { 1 };
+
+            let first = br.read(first_bits)? as usize;
+            *lengths.get_mut(first).ok_or_else(invalid_data)? = 1;
+
+            if count == 2 {
+                let second = br.read(8)? as usize;
+                *lengths.get_mut(second).ok_or_else(invalid_data)? = 1;
+            }
+
+            return Self::new(&lengths);
+        }
+
+        let mut code_lengths = [0u8; 19];
+        let count = br.read(4)? as usize + 4;
+
+        for &symbol in &CODE_LENGTH_ORDER[..count] {
+            code_lengths[symbol] = br.read(3)? as u8;
+        }
+
+        let code_length_code = Self::new(&code_lengths)?;
+
+        let mut max_symbol = if br.read(1)? == 1 {
+            let bits = 2 + 2 * br.read(3)?;
+            2 + br.read(bits)? as usize
+        } else {
+            alphabet
+        };
+
+        if max_symbol > alphabet {
+            return Err(invalid_data());
+        }
+
+        let mut previous = 8;
+        let mut symbol = 0;
+
+        while symbol < alphabet && max_symbol > 0 {
+            max_symbol -= 1;
+
+            let (length, repeat) = match code_length_code.read_symbol(br)? {
+                length @ 0..=15 => {
+                    if length != 0 {
+                        previous = length as u8;
+                    }
+
+                    (length as u8, 1)
+                },
+                16 => (previous, 3 + br.read(2)? as usize),
+                17 => (0, 3 + br.read(3)? as usize),
+                _ => (0, 11 + br.read(7)? as usize)
+            };
+
+            let lengths = lengths.get_mut(symbol..symbol + repeat).ok_or_else(invalid_data)?;
+            for l in lengths {
+                *l = length;
+            }
+
+            symbol += repeat;
+        }
+
+        Self::new(&lengths)
+    }
+
+    /// Builds the canonical prefix code of the given code lengths.
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject over-subscribed codes
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = 2 * left - count as i32;
+            if left < 0 {
+                return Err(invalid_data());
+            }
+        }
+
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|&symbol| lengths[symbol as usize] != 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+
+        if symbols.is_empty() {
+            return Err(invalid_data());
+        }
+
+        Ok(PrefixCode { counts, symbols })
+    }
+
+    /// Reads the next symbol from `br`.
+    fn read_symbol(&self, br: &mut BitReader) -> io::Result<u16> {
+        // A code with a single symbol takes no bits at all
+        if self.symbols.len() == 1 {
+            return Ok(self.symbols[0]);
+        }
+
+        let (mut code, mut first, mut index) = (0, 0, 0);
+
+        for &count in &self.counts[1..] {
+            code |= br.read(1)? as i32;
+            let count = count as i32;
+
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid_data())
+    }
+}
+
+/// Reads bits from a byte slice, least significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, pos: 0 }
+    }
+
+    /// Reads a `bits`-bit unsigned integer.
+    fn read(&mut self, bits: u32) -> io::Result<u32> {
+        let mut value = 0;
+
+        for i in 0..bits {
+            let byte = *self.data.get(self.pos / 8).ok_or_else(invalid_data)?;
+            value |= ((byte >> (self.pos % 8)) as u32 & 1) << i;
+            self.pos += 1;
+        }
+
+        Ok(value)
+    }
+}
+
+/// Reads the value of an _LZ77_ length or distance prefix.
+fn prefix_value(br: &mut BitReader, prefix: usize) -> io::Result<usize> {
+    if prefix < 4 {
+        return Ok(prefix + 1);
+    }
+
+    let extra = (prefix as u32 - 2) >> 1;
+    let offset = (2 + (prefix & 1)) << extra;
+
+    Ok(offset + br.read(extra)? as usize + 1)
+}
+
+/// Maps a distance code to a distance in pixels within an image of `xsize`
+/// pixels wide.
+fn distance(xsize: usize, code: usize) -> usize {
+    if code > DISTANCE_MAP.len() {
+        return code - DISTANCE_MAP.len();
+    }
+
+    let offset = DISTANCE_MAP[code - 1];
+    let (y, x) = ((offset >> 4) as isize, 8 - (offset & 0xf) as isize);
+
+    (y * xsize as isize + x).max(1) as usize
+}
+
+/// Returns the size of a dimension of `size` pixels after it is subsampled
+/// into blocks of `1 << bits` pixels.
+#[inline]
+fn subsample(size: usize, bits: u32) -> usize {
+    (size + (1 << bits) - 1) >> bits
+}
+
+/// Adds two pixels channel by channel, modulo 256.
+#[inline]
+fn add_pixels(a: u32, b: u32) -> u32 {
+    let alpha_green = (a & 0xff00_ff00).wrapping_add(b & 0xff00_ff00);
+    let red_blue = (a & 0x00ff_00ff).wrapping_add(b & 0x00ff_00ff);
+
+    (alpha_green & 0xff00_ff00) | (red_blue & 0x00ff_00ff)
+}
+
+/// Averages two pixels channel by channel, rounding down.
+#[inline]
+fn average(a: u32, b: u32) -> u32 {
+    (((a ^ b) & 0xfefe_fefe) >> 1) + (a & b)
+}
+
+/// Predicts a pixel as either `left` or `top`, whichever is closer to the
+/// gradient estimate `left + top - top_left`.
+fn select(left: u32, top: u32, top_left: u32) -> u32 {
+    let distance = |a: u32, b: u32| {
+        (0..4).map(|i| ((a >> (8 * i)) as u8 as i32 - (b >> (8 * i)) as u8 as i32).abs())
+            .sum::<i32>()
+    };
+
+    if distance(left, top_left) <= distance(top, top_left) {
+        top
+    } else {
+        left
+    }
+}
+
+/// Computes `f` for each channel of three pixels, clamping the results to
+/// the `0..=255` range.
+fn map_channels<F: Fn(i32, i32, i32) -> i32>(a: u32, b: u32, c: u32, f: F) -> u32 {
+    (0..4).fold(0, |px, i| {
+        let channel = |p: u32| (p >> (8 * i)) as u8 as i32;
+        let value = f(channel(a), channel(b), channel(c)).max(0).min(255) as u32;
+        px | (value << (8 * i))
+    })
+}
+
+#[inline]
+fn color_delta(multiplier: i8, color: i8) -> u8 {
+    ((multiplier as i32 * color as i32) >> 5) as u8
+}